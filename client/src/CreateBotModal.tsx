@@ -1,5 +1,6 @@
 import { Box, Button, Modal, TextField, Typography } from '@mui/material'
 import { FormEvent, useState } from 'react'
+import { ApiGame } from './api'
 
 const style = {
   position: 'absolute' as const,
@@ -12,8 +13,15 @@ const style = {
   p: 4,
 }
 
-export default function CreateBotModal(props: { open: boolean; handleClose: () => void; content: string }) {
-  const { open, handleClose, content } = props
+export default function CreateBotModal(props: {
+  open: boolean
+  handleClose: () => void
+  content: string
+  language: string
+  session: boolean
+  games: ApiGame[]
+}) {
+  const { open, handleClose, content, language, session, games } = props
   const [submitting, setSubmitting] = useState(false)
   const [error, setError] = useState(null as string | null)
 
@@ -21,16 +29,21 @@ export default function CreateBotModal(props: { open: boolean; handleClose: () =
     event.preventDefault()
     const formData = new FormData(event.target as HTMLFormElement)
     const botname = formData.get('botname') as string
-    const runType = 'Python'
     setSubmitting(true)
     setError(null)
     fetch('/api/bot', {
       method: 'POST',
       headers: { 'Content-Type': 'application/json' },
-      body: JSON.stringify({ name: botname, botcode: content, run_type: runType }),
+      body: JSON.stringify({
+        name: botname,
+        botcode: content,
+        run_type: 'Script',
+        language: language,
+        session: session,
+        games: games,
+      }),
     }).then((response) => {
       if (response.ok) {
-        console.log(response)
         handleClose()
       } else if (response.status === 400) {
         response.json().then((json) => {
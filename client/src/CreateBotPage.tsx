@@ -8,13 +8,16 @@ import {
   Accordion,
   AccordionDetails,
   AccordionSummary,
+  Checkbox,
   FormControl,
+  FormControlLabel,
   Link,
   MenuItem,
   Select,
   Typography,
 } from '@mui/material'
-import { Editor, SupportedLanguage } from './Editor'
+import { ApiGame, ApiLanguage, ApiMoveAnnotation, ApiResourceUsage, apiGames } from './api'
+import { Editor } from './Editor'
 
 const defaultPython = `print('Hello, I am a bot.')
 
@@ -32,10 +35,41 @@ print('Random num: ', num)
 print('rock')
 `
 
+const defaultJavaScript = `console.log('Hello, I am a bot.')
+
+// Using the input JSON (std is QuickJS's standard library)
+const inp = JSON.parse(std.in.getline())
+console.log(JSON.stringify(inp))
+
+// Generating random numbers
+const num = Math.floor(Math.random() * 3)
+console.log('Random num: ', num)
+
+// Chosen play must be the last line of output:
+console.log('rock')
+`
+
+const defaultCode: Record<string, string> = {
+  Python: defaultPython,
+  JavaScript: defaultJavaScript,
+}
+
+// Used until the server's list of languages has loaded.
+const defaultLanguages: ApiLanguage[] = [{ name: 'Python', editor_language: 'python', source_filename: 'main.py' }]
+
+const storageKey = (language: string): string => (language === 'Python' ? 'code' : `code-${language.toLowerCase()}`)
+
+const getStoredCode = (language: string): string =>
+  localStorage.getItem(storageKey(language)) || defaultCode[language] || ''
+
 enum BotPlay {
   Scissors = 'Scissors',
   Paper = 'Paper',
   Rock = 'Rock',
+  Lizard = 'Lizard',
+  Spock = 'Spock',
+  Cooperate = 'Cooperate',
+  Defect = 'Defect',
   Invalid = 'Invalid',
 }
 
@@ -46,6 +80,18 @@ interface TestResults {
   stderr: string
   stdin: string
   stdout: string
+  usage?: ApiResourceUsage | null
+  annotation?: ApiMoveAnnotation | null
+}
+
+const formatUsage = (usage: ApiResourceUsage): string => {
+  const fuelPercent = ((usage.fuel_consumed / usage.limits.fuel) * 100).toFixed(1)
+  const memoryMb = (usage.peak_memory / (1024 * 1024)).toFixed(1)
+  const instantiationMs = (usage.instantiation_time * 1000).toFixed(0)
+  return (
+    `Fuel: ${usage.fuel_consumed.toLocaleString()} of ${usage.limits.fuel.toLocaleString()} (${fuelPercent}%), ` +
+    `peak memory: ${memoryMb} of ${usage.limits.memory_mb}MiB, instantiation: ${instantiationMs}ms`
+  )
 }
 
 const getEmoji = (play?: BotPlay): string => {
@@ -56,17 +102,27 @@ const getEmoji = (play?: BotPlay): string => {
       return '📄'
     case BotPlay.Rock:
       return '🗿'
+    case BotPlay.Lizard:
+      return '🦎'
+    case BotPlay.Spock:
+      return '🖖'
+    case BotPlay.Cooperate:
+      return '🤝'
+    case BotPlay.Defect:
+      return '🗡️'
     default:
       return ''
   }
 }
 
-const startingCode = localStorage.getItem('code') || defaultPython
-
 function CreateBotPage() {
-  const [content, setContent] = React.useState(startingCode)
+  const [languages, setLanguages] = React.useState(defaultLanguages)
+  const [language, setLanguage] = React.useState('Python')
+  const [content, setContent] = React.useState(getStoredCode('Python'))
   const [testing, setTesting] = React.useState(false)
   const [testResults, setTestResults] = React.useState(null as TestResults | null)
+  const [session, setSession] = React.useState(false)
+  const [games, setGames] = React.useState(['RockPaperScissors'] as ApiGame[])
   const [open, setOpen] = React.useState(false)
   const handleOpen = () => setOpen(true)
   const handleClose = () => setOpen(false)
@@ -76,21 +132,29 @@ function CreateBotPage() {
   const handleUploadClose = () => setUploadOpen(false)
 
   useEffect(() => {
-    const code = localStorage.getItem('code')
-    if (code) {
-      setContent(code)
-    }
+    fetch('/api/languages')
+      .then((response) => response.json())
+      .then((json: ApiLanguage[]) => setLanguages(json))
+      .catch(() => setLanguages(defaultLanguages))
   }, [])
 
-  const onEdit = useCallback((content: string) => {
-    console.log('edited')
-    localStorage.setItem('code', content)
-    setContent(content)
-  }, [])
+  useEffect(() => {
+    setContent(getStoredCode(language))
+  }, [language])
+
+  const editorLanguage = languages.find((l) => l.name === language)?.editor_language || 'plaintext'
+
+  const onEdit = useCallback(
+    (content: string) => {
+      console.log('edited')
+      localStorage.setItem(storageKey(language), content)
+      setContent(content)
+    },
+    [language]
+  )
 
   const onSubmit = (event: FormEvent<HTMLFormElement>) => {
     event.preventDefault()
-    const runType = 'Python'
     setTesting(true)
     setTestResults(null)
 
@@ -98,18 +162,41 @@ function CreateBotPage() {
     const formData = new FormData(event.currentTarget)
     const roundNum = parseInt(formData.get('test-round') as string)
 
-    // Generate stdin json for the round
+    // Generate stdin json for the round, in the first game the bot plays
+    const game = games[0] || 'RockPaperScissors'
+    const pd = game === 'PrisonersDilemma'
+    const history = (pd ? ['Cooperate', 'Defect'] : ['Scissors', 'Rock']).slice(0, roundNum)
+    const opponentHistory = (pd ? ['Cooperate', 'Cooperate'] : ['Paper', 'Paper']).slice(0, roundNum)
+    const results = (pd ? ['Draw', 'Win'] : ['Win', 'Loss']).slice(0, roundNum)
     const stdin = JSON.stringify({
+      version: 3,
+      game: game,
       botname: 'My Bot',
       round: roundNum,
       opponent: 'Test Opponent Bot',
-      history: ['Scissors', 'Rock'].slice(0, roundNum),
+      history: history,
+      opponent_history: opponentHistory,
+      results: results,
+      score: {
+        wins: results.filter((r) => r === 'Win').length,
+        losses: results.filter((r) => r === 'Loss').length,
+        draws: results.filter((r) => r === 'Draw').length,
+      },
+      match_id: null,
+      tournament_id: null,
     })
 
     fetch('/api/test', {
       method: 'POST',
       headers: { 'Content-Type': 'application/json' },
-      body: JSON.stringify({ botcode: content, run_type: runType, stdin: stdin }),
+      body: JSON.stringify({
+        botcode: content,
+        run_type: 'Script',
+        language: language,
+        stdin: stdin,
+        session: session,
+        game: game,
+      }),
     })
       .then((response) => {
         setTesting(false)
@@ -137,7 +224,14 @@ function CreateBotPage() {
 
   return (
     <Box pb={2} maxWidth={'900px'} margin={'auto'}>
-      <CreateBotModal open={open} handleClose={handleClose} content={content} />
+      <CreateBotModal
+        open={open}
+        handleClose={handleClose}
+        content={content}
+        language={language}
+        session={session}
+        games={games}
+      />
       <UploadWasmModal open={uploadOpen} handleClose={handleUploadClose} />
       <Box py={2}>
         <Typography variant="h3" component={'h2'} sx={{ py: 1, fontSize: '18pt' }}>
@@ -172,6 +266,12 @@ function CreateBotPage() {
           "scissors" with nothing else on the same line. The program may write other information to stdout or stderr, but
           everything except the last line of stdout will be ignored.`}
         </Typography>
+        <Typography pb={2}>
+          {`Some tournaments are played as rock-paper-scissors-lizard-Spock instead, where "lizard" and "spock" are
+          valid plays too. Others are an iterated prisoner's dilemma, where the only valid plays are "cooperate" and
+          "defect", and matches last for a couple of hundred rounds. The "game" field of the JSON input below says which
+          game is being played.`}
+        </Typography>
         <Accordion>
           <AccordionSummary expandIcon={<ExpandMoreIcon />} aria-controls="panel1a-content" id="panel1a-header">
             <Typography sx={{ fontSize: '12pt', fontWeight: 400 }}>Optional JSON input</Typography>
@@ -184,33 +284,101 @@ function CreateBotPage() {
             </Typography>
             <pre>
               {`{
+  "version": 3,            // Version of this input format. New fields may be added in later versions.
+  "game": "RockPaperScissors", // Or "RockPaperScissorsLizardSpock" or "PrisonersDilemma", see below.
   "botname": "My Bot",     // This is your own bot's name
   "round": 2,              // Round number for this battle, starting at 0. E.g. 2 for the third round.
   "opponent": "RandomBot", // Opponent's name
-  "history": [             // The plays previously made by the opponent during this battle.
+  "history": [             // The plays previously made by your bot during this battle.
     "Scissors",            // Note the capitalisation.
     "Rock"
-  ]
+  ],
+  "opponent_history": [    // The plays previously made by the opponent during this battle.
+    "Paper",
+    "Paper"
+  ],
+  "results": [             // The result of each previous round for your bot: "Win", "Loss" or "Draw".
+    "Win",
+    "Loss"
+  ],
+  "score": { "wins": 1, "losses": 1, "draws": 0 },
+  "match_id": "MyBot-RandomBot", // Identifies this battle (null when testing)
+  "tournament_id": 42            // Identifies this tournament (null when testing)
 }`}
             </pre>
           </AccordionDetails>
         </Accordion>
+        <Accordion>
+          <AccordionSummary expandIcon={<ExpandMoreIcon />} aria-controls="panel3a-content" id="panel3a-header">
+            <Typography sx={{ fontSize: '12pt', fontWeight: 400 }}>Optional JSON output</Typography>
+          </AccordionSummary>
+          <AccordionDetails>
+            <Typography pt={1}>
+              {`Instead of just the play, the last line of stdout may be a JSON object with the play and some comments
+              on it. Spectators can see them by hovering over the bot's play in the tournament.`}
+            </Typography>
+            <pre>
+              {`{
+  "move": "rock",          // The play, as it would be printed on its own.
+  "confidence": 0.7,       // Optional: how sure your bot is, from 0 to 1.
+  "note": "They always open with scissors" // Optional: up to 280 characters.
+}`}
+            </pre>
+          </AccordionDetails>
+        </Accordion>
+        <Accordion>
+          <AccordionSummary expandIcon={<ExpandMoreIcon />} aria-controls="panel2a-content" id="panel2a-header">
+            <Typography sx={{ fontSize: '12pt', fontWeight: 400 }}>Optional session mode</Typography>
+          </AccordionSummary>
+          <AccordionDetails>
+            <Typography pt={1}>
+              {`By default a bot is started from scratch for every round. In session mode it's started once per battle
+              and keeps running, so it can remember things between rounds. Each round, the JSON input above is written
+              to stdin as a single line, and the bot must answer by printing its play on a line of its own (other lines
+              are ignored). Remember to flush stdout after printing the play. Each round has its own time and
+              instruction limits, and the first round's limits also cover the bot starting up.`}
+            </Typography>
+            <pre>
+              {`import json, sys
+
+for line in sys.stdin:
+    inp = json.loads(line)
+    print('rock', flush=True)`}
+            </pre>
+          </AccordionDetails>
+        </Accordion>
       </Box>
       <Typography variant="h3" component={'h2'} sx={{ py: 1, fontSize: '18pt' }}>
         Upload a WebAssembly bot
       </Typography>
       <Typography py={1}>
-        {`Use any any programming language, so long as you can compile it to a single .wasm file (WebAssembly with WASI bindings). See the `}
+        {`Use any any programming language, so long as you can compile it to a single .wasm file (WebAssembly with WASI bindings, either a preview1 module or a preview2 component). See the `}
         <a href="https://github.com/katharosada/wasm-snippy/tree/main/sample-bots">example bots with instructions</a>.
       </Typography>
       <Button variant="contained" color="secondary" onClick={handleUploadOpen}>
         Enter with a .wasm file
       </Button>
       <Typography variant="h3" sx={{ pt: 2, pb: 1, fontSize: '14pt', fontWeight: 400 }}>
-        {`Or submit a Python script instead`}
+        {`Or submit a script instead`}
       </Typography>
-      <Typography py={1}>{`Python scripts will be executed using CPython 3.11 compiled to WebAssembly.`}</Typography>
-      <Editor language={SupportedLanguage.PYTHON} initialContent={startingCode} onEdit={onEdit} />
+      <Typography py={1}>
+        {`Scripts are run by an interpreter compiled to WebAssembly, e.g. CPython 3.11 for Python and QuickJS for JavaScript.`}
+      </Typography>
+      <Typography py={1}>
+        {`Python bots with helper modules or data files can be uploaded as a .zip archive instead, with main.py at the top level.`}
+      </Typography>
+      <Box pb={1}>
+        <FormControl size="small">
+          <Select value={language} id="language" onChange={(event) => setLanguage(event.target.value)}>
+            {languages.map((l) => (
+              <MenuItem key={l.name} value={l.name}>
+                {l.name}
+              </MenuItem>
+            ))}
+          </Select>
+        </FormControl>
+      </Box>
+      <Editor key={language} language={editorLanguage} initialContent={getStoredCode(language)} onEdit={onEdit} />
       <Box py={2}>
         <form onSubmit={onSubmit}>
           <Box py={1}>
@@ -221,6 +389,30 @@ function CreateBotPage() {
                 <MenuItem value="2">Round 2</MenuItem>
               </Select>
             </FormControl>
+            <FormControlLabel
+              sx={{ px: 1 }}
+              control={<Checkbox checked={session} onChange={(event) => setSession(event.target.checked)} />}
+              label="Session mode"
+            />
+            {apiGames.map(({ game, name }) => (
+              <FormControlLabel
+                key={game}
+                sx={{ px: 1 }}
+                control={
+                  <Checkbox
+                    checked={games.includes(game)}
+                    onChange={(event) =>
+                      setGames(
+                        apiGames
+                          .map((g) => g.game)
+                          .filter((g) => (g === game ? event.target.checked : games.includes(g)))
+                      )
+                    }
+                  />
+                }
+                label={name}
+              />
+            ))}
             <Button variant="contained" type="submit" disabled={testing}>
               &nbsp;Test&nbsp;
             </Button>
@@ -238,6 +430,19 @@ function CreateBotPage() {
             (testResults?.result || '') +
             (testResults?.invalid_reason ? ` (${testResults.invalid_reason})` : '')}
         </Typography>
+        {testResults?.annotation && (
+          <Typography>
+            {testResults.annotation.confidence !== null &&
+              `Confidence: ${Math.round(testResults.annotation.confidence * 100)}%. `}
+            {testResults.annotation.note && `Note: ${testResults.annotation.note}`}
+          </Typography>
+        )}
+        {testResults?.usage && (
+          <Typography>
+            {`Time: ${(testResults.duration * 1000).toFixed(0)} of ${testResults.usage.limits.time_ms}ms. `}
+            {formatUsage(testResults.usage)}
+          </Typography>
+        )}
         <Typography>Stdout</Typography>
         <Box bgcolor={'#DDD'} sx={{ minHeight: 50, borderRadius: '5px', mb: 3, px: 2, py: 1 }}>
           <pre>{testResults?.stdout}</pre>
@@ -3,12 +3,8 @@ import './monacoWorkers'
 import * as monaco from 'monaco-editor/esm/vs/editor/editor.api'
 import { useEffect, useRef, useState } from 'react'
 
-export enum SupportedLanguage {
-  PYTHON = 'python',
-}
-
 export const Editor = (props: {
-  language: SupportedLanguage
+  language: string
   initialContent: string
   onEdit: (newContent: string) => void
 }) => {
@@ -1,8 +1,18 @@
 import React, { useEffect, useState } from 'react'
+import { ApiLeaderboard } from './api'
+import { Box, Button, Table, TableBody, TableCell, TableHead, TableRow, Typography } from '@mui/material'
 
-async function fetchBotList() {
+// Average instructions per move, e.g. 1.2M.
+function formatEfficiency(efficiency: number | null): string {
+  if (efficiency === null) {
+    return '-'
+  }
+  return Intl.NumberFormat('en', { notation: 'compact', maximumFractionDigits: 1 }).format(efficiency)
+}
+
+async function fetchBotList(page: number): Promise<ApiLeaderboard> {
   // use fetch to get json data from //api/bots
-  const response = await fetch('/api/bots')
+  const response = await fetch(`/api/bots?page=${page}`)
   if (!response.ok) {
     throw new Error(`HTTP error! status: ${response.status}`)
   }
@@ -11,24 +21,66 @@ async function fetchBotList() {
 }
 
 function LeaderboardPage() {
-  const [botList, setBotList] = useState([] as any[])
+  const [page, setPage] = useState(1)
+  const [leaderboard, setLeaderboard] = useState(null as ApiLeaderboard | null)
 
   useEffect(() => {
     // fetch bots and put list in state
-    fetchBotList().then((list) => setBotList(list))
-  }, [])
+    fetchBotList(page).then((leaderboard) => setLeaderboard(leaderboard))
+  }, [page])
 
-  if (botList.length === 0) {
+  if (!leaderboard) {
     return <div>Loading...</div>
   }
 
+  const lastPage = Math.max(1, Math.ceil(leaderboard.total / leaderboard.page_size))
+
   return (
-    <div>
-      <h1>Leaderboard</h1>
-      {botList.map((bot) => {
-        return <div key={bot.name}>{bot.name}</div>
-      })}
-    </div>
+    <Box py={2}>
+      <Typography variant="h3" component={'h2'} sx={{ py: 1, fontSize: '18pt' }}>
+        Leaderboard
+      </Typography>
+      <Table size="small">
+        <TableHead>
+          <TableRow>
+            <TableCell>#</TableCell>
+            <TableCell>Bot</TableCell>
+            <TableCell>Type</TableCell>
+            <TableCell align="right">Rating</TableCell>
+            <TableCell align="right">W / L / D</TableCell>
+            <TableCell align="right">Tournaments won</TableCell>
+            <TableCell align="right" title="Average Wasm instructions per move">
+              Efficiency
+            </TableCell>
+          </TableRow>
+        </TableHead>
+        <TableBody>
+          {leaderboard.bots.map((bot) => {
+            return (
+              <TableRow key={bot.name}>
+                <TableCell>{bot.rank}</TableCell>
+                <TableCell>{bot.name}</TableCell>
+                <TableCell>{bot.run_type}</TableCell>
+                <TableCell align="right">{Math.round(bot.rating)}</TableCell>
+                <TableCell align="right">
+                  {bot.wins} / {bot.losses} / {bot.draws}
+                </TableCell>
+                <TableCell align="right">{bot.tournaments_won}</TableCell>
+                <TableCell align="right">{formatEfficiency(bot.efficiency)}</TableCell>
+              </TableRow>
+            )
+          })}
+        </TableBody>
+      </Table>
+      <Box py={1}>
+        <Button disabled={page <= 1} onClick={() => setPage(page - 1)}>
+          Previous
+        </Button>
+        <Button disabled={page >= lastPage} onClick={() => setPage(page + 1)}>
+          Next
+        </Button>
+      </Box>
+    </Box>
   )
 }
 
@@ -1,7 +1,18 @@
 import './App.css'
-import { ApiMatchOutcome, ApiTournament, SPROutcome } from './api'
+import {
+  ApiGame,
+  ApiMatchOutcome,
+  ApiMoveAnnotation,
+  ApiStandingsEntry,
+  ApiStandingsUpdate,
+  ApiTournament,
+  ApiTournamentConfig,
+  ApiTournamentFormat,
+  SPROutcome,
+} from './api'
 import { Box, Typography } from '@mui/material'
 import { Match, Tournament } from './Tournament'
+import { Standings } from './Standings'
 import { useEffect, useState } from 'react'
 
 const stateConverter = {
@@ -23,6 +34,14 @@ const convertToEmoji = (choices: SPROutcome[]): string[] => {
         return '📄'
       case 'Rock':
         return '🗿'
+      case 'Lizard':
+        return '🦎'
+      case 'Spock':
+        return '🖖'
+      case 'Cooperate':
+        return '🤝'
+      case 'Defect':
+        return '🗡️'
       default:
         return 'invalid'
     }
@@ -31,7 +50,6 @@ const convertToEmoji = (choices: SPROutcome[]): string[] => {
 }
 
 function convertMatches(tournament: ApiTournament): Match[] {
-  console.log(tournament)
   const matches = tournament.starting_matches.map((apiMatch) => {
     const participants = apiMatch.participants.map((participant) => {
       return {
@@ -45,6 +63,8 @@ function convertMatches(tournament: ApiTournament): Match[] {
     return {
       id: apiMatch.id,
       nextMatchId: apiMatch.next_match_id,
+      nextLooserMatchId: apiMatch.next_loser_match_id,
+      bracket: apiMatch.bracket,
       tournamentRoundText: apiMatch.tournament_round_text,
       startTime: '',
       state: stateConverter[apiMatch.state],
@@ -55,8 +75,56 @@ function convertMatches(tournament: ApiTournament): Match[] {
   return matches
 }
 
+const describeAnnotation = (annotation: ApiMoveAnnotation | null): string | undefined => {
+  if (!annotation) {
+    return undefined
+  }
+  const parts: string[] = []
+  if (annotation.confidence !== null) {
+    parts.push(`${Math.round(annotation.confidence * 100)}% sure`)
+  }
+  if (annotation.note) {
+    parts.push(annotation.note)
+  }
+  return parts.join(': ')
+}
+
+const tiebreakText = {
+  CoinToss: 'a coin toss',
+  Fuel: 'the bot that used fewer instructions',
+  Replay: 'replaying the match',
+  SuddenDeath: 'sudden death',
+}
+
+const describeMatchRules = (config: ApiTournamentConfig): string => {
+  const rules = config.match_rules
+  const parts: string[] = []
+  if (rules.rounds !== null) {
+    parts.push(`Matches last ${rules.rounds} rounds.`)
+  } else if (config.game === 'PrisonersDilemma') {
+    const { min_rounds, max_rounds } = config.prisoners_dilemma
+    parts.push(`Matches last ${min_rounds} to ${Math.max(min_rounds, max_rounds)} rounds.`)
+  }
+  if (rules.win_threshold !== null) {
+    parts.push(`First to ${rules.win_threshold} wins.`)
+  } else if (config.game === 'PrisonersDilemma') {
+    parts.push('Every round is played.')
+  }
+  if (!rules.ties_count_as_rounds) {
+    parts.push("Tied rounds don't count.")
+  }
+  if (config.format === 'SingleElimination' || config.format === 'DoubleElimination') {
+    parts.push(`Tied matches are decided by ${tiebreakText[config.tiebreak]}.`)
+  }
+  return parts.join(' ')
+}
+
 function LiveTournamentPage() {
   const [matches, setMatches] = useState(null as any)
+  const [format, setFormat] = useState('SingleElimination' as ApiTournamentFormat)
+  const [game, setGame] = useState('RockPaperScissors' as ApiGame)
+  const [matchRules, setMatchRules] = useState('')
+  const [standings, setStandings] = useState([] as ApiStandingsEntry[])
   const [sock, setSock] = useState(null as WebSocket | null)
 
   useEffect(() => {
@@ -81,6 +149,10 @@ function LiveTournamentPage() {
       } else if (json['starting_matches']) {
         // First message affter connection is the full tournament state.
         const tournament = json as ApiTournament
+        setFormat(tournament.config.format)
+        setGame(tournament.config.game)
+        setMatchRules(describeMatchRules(tournament.config))
+        setStandings(tournament.standings)
         setMatches(convertMatches(tournament))
         for (const matchOutcome of tournament.match_updates) {
           setMatches((matches: Match[]) => {
@@ -93,6 +165,7 @@ function LiveTournamentPage() {
                     name: participant.name,
                     isWinner: participant.winner && match.state !== 'WALK_OVER',
                     resultText: convertToEmoji(participant.moves),
+                    annotations: participant.annotations.map(describeAnnotation),
                   }
                 })
               }
@@ -100,6 +173,9 @@ function LiveTournamentPage() {
             })
           })
         }
+      } else if (json['standings']) {
+        // Formats with a standings table send the updated table after every match.
+        setStandings((json as ApiStandingsUpdate).standings)
       } else {
         // Other messages update specific match states.
         const matchOutcome = json as ApiMatchOutcome
@@ -113,6 +189,7 @@ function LiveTournamentPage() {
                   name: participant.name,
                   isWinner: participant.winner && match.state !== 'WALK_OVER',
                   resultText: convertToEmoji(participant.moves),
+                  annotations: participant.annotations.map(describeAnnotation),
                 }
               })
             }
@@ -164,9 +241,10 @@ function LiveTournamentPage() {
           <br />
           Bots that return an invalid play will be removed from future tournaments.
         </Typography>
-        <Typography sx={{ py: 1, fontSize: '12pt' }}></Typography>
+        <Typography sx={{ py: 1, fontSize: '12pt' }}>{matchRules}</Typography>
       </Box>
-      <Tournament matches={matches} />
+      <Standings standings={standings} showPayoff={game === 'PrisonersDilemma'} />
+      <Tournament matches={matches} format={format} />
     </Box>
   )
 }
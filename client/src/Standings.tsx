@@ -0,0 +1,43 @@
+import { ApiStandingsEntry } from './api'
+import { Table, TableBody, TableCell, TableHead, TableRow } from '@mui/material'
+
+export const Standings = (props: { standings: ApiStandingsEntry[]; showPayoff?: boolean }) => {
+  if (!props.standings || props.standings.length === 0) {
+    return null
+  }
+
+  return (
+    <Table size="small">
+      <TableHead>
+        <TableRow>
+          <TableCell>#</TableCell>
+          <TableCell>Bot</TableCell>
+          <TableCell align="right">Played</TableCell>
+          <TableCell align="right">W / D / L</TableCell>
+          <TableCell align="right">Rounds</TableCell>
+          <TableCell align="right">Points</TableCell>
+          {props.showPayoff && <TableCell align="right">Payoff</TableCell>}
+        </TableRow>
+      </TableHead>
+      <TableBody>
+        {props.standings.map((entry) => {
+          return (
+            <TableRow key={entry.name}>
+              <TableCell>{entry.rank}</TableCell>
+              <TableCell>{entry.name}</TableCell>
+              <TableCell align="right">{entry.played}</TableCell>
+              <TableCell align="right">
+                {entry.wins} / {entry.draws} / {entry.losses}
+              </TableCell>
+              <TableCell align="right">
+                {entry.rounds_won} - {entry.rounds_lost}
+              </TableCell>
+              <TableCell align="right">{entry.points}</TableCell>
+              {props.showPayoff && <TableCell align="right">{entry.payoff}</TableCell>}
+            </TableRow>
+          )
+        })}
+      </TableBody>
+    </Table>
+  )
+}
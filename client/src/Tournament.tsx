@@ -1,5 +1,6 @@
 import CircularProgress from '@mui/material/CircularProgress'
-import { SingleEliminationBracket } from 'react-tournament-brackets'
+import { ApiBracket, ApiTournamentFormat } from './api'
+import { DoubleEliminationBracket, SingleEliminationBracket } from 'react-tournament-brackets'
 
 import './Tournament.css'
 
@@ -8,11 +9,14 @@ export interface Party {
   name?: string
   isWinner?: boolean
   resultText?: any // Actually it's string[] but the library API wants a string and it's not compatible.
+  annotations?: (string | undefined)[] // What the bot said about each move, shown on hover.
 }
 
 export interface Match {
   id: number | string
   nextMatchId: number | string | null
+  nextLooserMatchId?: number | string | null
+  bracket?: ApiBracket
   tournamentRoundText?: string
   startTime: string
   state: string
@@ -28,7 +32,7 @@ export const MatchParticipant = (props: { party: Party; inProgress: boolean }) =
       <div style={{ display: 'flex' }}>
         {resultText.map((result, i) => {
           return (
-            <div key={i} style={{ minWidth: '20px' }}>
+            <div key={i} style={{ minWidth: '20px' }} title={party.annotations?.[i]}>
               {result}
             </div>
           )
@@ -51,10 +55,54 @@ export const Match = (props: { match: Match; topParty: Party; bottomParty: Party
   )
 }
 
-export const Tournament = (props: { matches: Match[] }) => {
+const MatchList = (props: { matches: Match[] }) => {
+  const rounds: string[] = []
+  for (const match of props.matches) {
+    const round = match.tournamentRoundText || ''
+    if (!rounds.includes(round)) {
+      rounds.push(round)
+    }
+  }
+  return (
+    <div className="matchrounds">
+      {rounds.map((round) => {
+        return (
+          <div key={round} className="matchround">
+            <div className="matchroundtitle">Round {round}</div>
+            {props.matches
+              .filter((match) => (match.tournamentRoundText || '') === round)
+              .map((match) => {
+                const [topParty, bottomParty] = match.participants
+                return (
+                  <Match
+                    key={match.id}
+                    match={match}
+                    topParty={topParty || { id: '' }}
+                    bottomParty={bottomParty || { id: '' }}
+                  />
+                )
+              })}
+          </div>
+        )
+      })}
+    </div>
+  )
+}
+
+export const Tournament = (props: { matches: Match[]; format?: ApiTournamentFormat }) => {
   if (!props.matches || props.matches.length === 0) {
     return <div>No tournament running.</div>
   }
 
+  if (props.format === 'RoundRobin' || props.format === 'Swiss') {
+    return <MatchList matches={props.matches} />
+  }
+  if (props.format === 'DoubleElimination') {
+    const brackets = {
+      upper: props.matches.filter((match) => match.bracket !== 'Lower'),
+      lower: props.matches.filter((match) => match.bracket === 'Lower'),
+    }
+    return <DoubleEliminationBracket matches={brackets} matchComponent={Match} />
+  }
   return <SingleEliminationBracket matches={props.matches} matchComponent={Match} />
 }
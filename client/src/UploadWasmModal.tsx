@@ -1,5 +1,17 @@
-import { Box, Button, Modal, TextField, Typography } from '@mui/material'
+import {
+  Box,
+  Button,
+  Checkbox,
+  FormControl,
+  FormControlLabel,
+  MenuItem,
+  Modal,
+  Select,
+  TextField,
+  Typography,
+} from '@mui/material'
 import { FormEvent, useRef, useState } from 'react'
+import { apiGames } from './api'
 
 const style = {
   position: 'absolute' as const,
@@ -30,7 +42,6 @@ export default function UploadWasmModal(props: { open: boolean; handleClose: ()
       body: formData,
     }).then((response) => {
       if (response.ok) {
-        console.log(response)
         handleClose()
       } else if (response.status === 400) {
         response.json().then((json) => {
@@ -77,10 +88,28 @@ export default function UploadWasmModal(props: { open: boolean; handleClose: ()
           <p style={{ fontFamily: 'monospace', fontSize: '12pt' }}>{selectedFileName}</p>
           <label htmlFor="raised-button-file">
             <Button variant="contained" component="span">
-              {selectedFileName ? 'Change file' : 'Select wasm file'}
+              {selectedFileName ? 'Change file' : 'Select file'}
             </Button>
           </label>
           <br />
+          <FormControl size="small" sx={{ mb: 1 }}>
+            <Select defaultValue="Wasi" id="run_type" name="run_type">
+              <MenuItem value="Wasi">Command (reads stdin, prints its play)</MenuItem>
+              <MenuItem value="Component">snippy:bot/game-player or player component</MenuItem>
+              <MenuItem value="Python">Python (.zip archive with main.py)</MenuItem>
+            </Select>
+          </FormControl>
+          <br />
+          <FormControlLabel control={<Checkbox name="session" value="true" />} label="Session mode" />
+          <br />
+          {apiGames.map(({ game, name }) => (
+            <FormControlLabel
+              key={game}
+              control={<Checkbox name="games" value={game} defaultChecked={game === 'RockPaperScissors'} />}
+              label={name}
+            />
+          ))}
+          <br />
           <Typography sx={{ mt: 2, color: 'red' }}>{error}&nbsp;</Typography>
           <Button type="submit" variant="contained" color="secondary" disabled={submitting}>
             Enter tournament
@@ -1,13 +1,88 @@
+export type ApiTournamentFormat = 'SingleElimination' | 'DoubleElimination' | 'RoundRobin' | 'Swiss'
+
+export type ApiGame = 'RockPaperScissors' | 'RockPaperScissorsLizardSpock' | 'PrisonersDilemma'
+
+export const apiGames: { game: ApiGame; name: string }[] = [
+  { game: 'RockPaperScissors', name: 'Rock-paper-scissors' },
+  { game: 'RockPaperScissorsLizardSpock', name: 'Rock-paper-scissors-lizard-Spock' },
+  { game: 'PrisonersDilemma', name: "Prisoner's dilemma" },
+]
+
+export interface ApiBotLimits {
+  time_ms: number
+  fuel: number
+  memory_mb: number
+  output_kb: number
+}
+
+export interface ApiResourceUsage {
+  fuel_consumed: number
+  peak_memory: number
+  instantiation_time: number
+  limits: ApiBotLimits
+}
+
+export type ApiTiebreak = 'CoinToss' | 'Fuel' | 'Replay' | 'SuddenDeath'
+
+export interface ApiMatchRules {
+  rounds: number | null
+  win_threshold: number | null
+  ties_count_as_rounds: boolean
+}
+
+export interface ApiTournamentConfig {
+  game: ApiGame
+  format: ApiTournamentFormat
+  swiss_rounds: number | null
+  match_rules: ApiMatchRules
+  tiebreak: ApiTiebreak
+  seed: number | null
+  prisoners_dilemma: ApiPrisonersDilemma
+}
+
+export interface ApiPrisonersDilemma {
+  payoffs: { temptation: number; reward: number; punishment: number; sucker: number }
+  min_rounds: number
+  max_rounds: number
+}
+
 export interface ApiTournament {
+  id: number | null
+  config: ApiTournamentConfig
   starting_matches: ApiMatch[]
   match_updates: ApiMatchOutcome[]
+  standings: ApiStandingsEntry[]
+}
+
+export interface ApiStandingsEntry {
+  rank: number
+  name: string
+  played: number
+  wins: number
+  draws: number
+  losses: number
+  points: number
+  rounds_won: number
+  rounds_lost: number
+  payoff: number
 }
 
-export type SPROutcome = 'Scissors' | 'Paper' | 'Rock' | 'Invalid'
+export interface ApiStandingsUpdate {
+  standings: ApiStandingsEntry[]
+}
+
+export type SPROutcome = 'Scissors' | 'Paper' | 'Rock' | 'Lizard' | 'Spock' | 'Cooperate' | 'Defect' | 'Invalid'
+
+export interface ApiMoveAnnotation {
+  confidence: number | null
+  note: string | null
+}
 
 export interface ApiParticipantOutcome {
   name: string
   moves: SPROutcome[]
+  fuel: (number | null)[]
+  annotations: (ApiMoveAnnotation | null)[]
   winner: boolean
 }
 
@@ -15,6 +90,7 @@ export interface ApiMatchOutcome {
   match_id: string
   state: 'NotStarted' | 'InProgress' | 'Bye' | 'Finished'
   winner: number
+  draw: boolean
   note?: string
   participants: ApiParticipantOutcome[]
   bot1_moves: SPROutcome[]
@@ -22,16 +98,63 @@ export interface ApiMatchOutcome {
 }
 
 export interface ApiBotDetails {
-  run_type: 'Python' | 'Wasi'
+  run_type: 'Python' | 'Wasi' | 'Component' | 'JavaScript' | 'Script'
   name: string
   code: string
   wasm_path: string
+  wasm_kind: 'CoreModule' | 'Component'
+  session: boolean
 }
 
+export interface ApiLanguage {
+  name: string
+  editor_language: string | null
+  source_filename: string
+}
+
+export type ApiBracket = 'Upper' | 'Lower' | 'GrandFinal' | 'GrandFinalReset'
+
 export interface ApiMatch {
   id: string
   tournament_round_text: string
   next_match_id: string | null
+  next_loser_match_id: string | null
+  bracket: ApiBracket
   participants: ApiBotDetails[]
   state: 'NotStarted' | 'InProgress' | 'Bye' | 'Finished'
 }
+
+export interface ApiLeaderboardEntry {
+  rank: number
+  name: string
+  run_type: 'Python' | 'Wasi' | 'Component' | 'JavaScript' | 'Script'
+  rating: number
+  wins: number
+  losses: number
+  draws: number
+  tournaments_won: number
+  is_builtin: boolean
+  efficiency: number | null
+}
+
+export interface ApiLeaderboard {
+  bots: ApiLeaderboardEntry[]
+  total: number
+  page: number
+  page_size: number
+}
+
+export interface ApiTournamentSummary {
+  id: number
+  started_at: string
+  finished_at: string | null
+  winner: string | null
+  num_matches: number
+}
+
+export interface ApiTournamentList {
+  tournaments: ApiTournamentSummary[]
+  total: number
+  page: number
+  page_size: number
+}
@@ -0,0 +1,28 @@
+wit_bindgen::generate!({
+    path: "../../wasi-runner/wit/player.wit",
+    world: "player",
+});
+
+struct Bot;
+
+impl Guest for Bot {
+    fn play(state: GameState) -> Turn {
+        // Play whatever beats the opponent's last move.
+        let (play, reason) = match state.opponent_history.last() {
+            Some(Move::Rock) => (Move::Paper, "opponent last played rock"),
+            Some(Move::Paper) => (Move::Scissors, "opponent last played paper"),
+            Some(Move::Scissors) => (Move::Rock, "opponent last played scissors"),
+            None => (Move::Rock, "first round"),
+        };
+
+        Turn {
+            move_: play,
+            debug: vec![DebugEntry {
+                key: "reason".to_string(),
+                value: reason.to_string(),
+            }],
+        }
+    }
+}
+
+export!(Bot);
@@ -1,3 +1,4 @@
+DROP TABLE IF EXISTS match_moves, match_participants, tournament_matches, tournaments;
 DROP TABLE IF EXISTS bots;
 DROP USER IF EXISTS snippyuser;
 
@@ -22,4 +23,92 @@ INSERT INTO bots (name, run_type, script_contents, wasm_path, is_builtin) VALUES
 INSERT INTO bots (name, run_type, script_contents, wasm_path, is_builtin) VALUES ('Snippy snap', 2, 'print(''scissors'')', '', true);
 
 -- Add colum to bots table for disabling bots
-ALTER TABLE bots ADD is_disabled BOOLEAN NOT NULL DEFAULT FALSE;
\ No newline at end of file
+ALTER TABLE bots ADD is_disabled BOOLEAN NOT NULL DEFAULT FALSE;
+
+-- Add columns to bots table for leaderboard ratings and results
+ALTER TABLE bots ADD rating DOUBLE PRECISION NOT NULL DEFAULT 1000;
+ALTER TABLE bots ADD wins INT NOT NULL DEFAULT 0;
+ALTER TABLE bots ADD losses INT NOT NULL DEFAULT 0;
+ALTER TABLE bots ADD draws INT NOT NULL DEFAULT 0;
+ALTER TABLE bots ADD tournaments_won INT NOT NULL DEFAULT 0;
+
+-- Tournament history
+CREATE TABLE tournaments (
+    id SERIAL PRIMARY KEY,
+    started_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    finished_at TIMESTAMP,
+    winner_bot_id INT REFERENCES bots(id) ON DELETE SET NULL
+);
+
+CREATE TABLE tournament_matches (
+    id SERIAL PRIMARY KEY,
+    tournament_id INT NOT NULL REFERENCES tournaments(id) ON DELETE CASCADE,
+    match_key TEXT NOT NULL,
+    match_order INT NOT NULL,
+    tournament_round_text VARCHAR(255) NOT NULL,
+    next_match_key TEXT,
+    state INT NOT NULL,
+    winner INT,
+    note TEXT,
+    UNIQUE (tournament_id, match_key)
+);
+
+CREATE TABLE match_participants (
+    match_id INT NOT NULL REFERENCES tournament_matches(id) ON DELETE CASCADE,
+    position INT NOT NULL,
+    bot_id INT REFERENCES bots(id) ON DELETE SET NULL,
+    bot_name VARCHAR(255) NOT NULL,
+    is_starting BOOLEAN NOT NULL DEFAULT FALSE,
+    is_winner BOOLEAN NOT NULL DEFAULT FALSE,
+    PRIMARY KEY (match_id, position)
+);
+
+CREATE TABLE match_moves (
+    match_id INT NOT NULL REFERENCES tournament_matches(id) ON DELETE CASCADE,
+    round INT NOT NULL,
+    position INT NOT NULL,
+    play INT NOT NULL,
+    PRIMARY KEY (match_id, round, position)
+);
+GRANT ALL PRIVILEGES ON TABLE tournaments, tournament_matches, match_participants, match_moves TO snippyuser;
+GRANT USAGE, SELECT ON ALL SEQUENCES IN SCHEMA public to snippyuser;
+
+-- Tournament formats and drawn matches
+ALTER TABLE tournaments ADD format INT NOT NULL DEFAULT 1;
+ALTER TABLE tournament_matches ADD is_draw BOOLEAN NOT NULL DEFAULT FALSE;
+
+-- Tournament settings, as JSON
+ALTER TABLE tournaments ADD config TEXT;
+
+-- Double elimination brackets
+ALTER TABLE tournament_matches ADD next_loser_match_key TEXT;
+ALTER TABLE tournament_matches ADD bracket INT NOT NULL DEFAULT 0;
+
+-- Session bots keep one instance running for a whole match
+ALTER TABLE bots ADD is_session BOOLEAN NOT NULL DEFAULT FALSE;
+
+-- Uploaded bots can be core modules (1) or components (2)
+ALTER TABLE bots ADD wasm_kind INT NOT NULL DEFAULT 1;
+
+-- Interpreter that runs a Script bot (run_type 5), from interpreters.json
+ALTER TABLE bots ADD language TEXT NOT NULL DEFAULT '';
+
+-- Fuel used by each move, when it was measured
+ALTER TABLE match_moves ADD fuel BIGINT;
+
+-- Fuel totals for the leaderboard's efficiency column
+ALTER TABLE bots ADD fuel_used BIGINT NOT NULL DEFAULT 0;
+ALTER TABLE bots ADD fuel_moves INT NOT NULL DEFAULT 0;
+
+-- What bots that print their play as JSON said about each move
+ALTER TABLE match_moves ADD confidence REAL;
+ALTER TABLE match_moves ADD note TEXT;
+
+-- Games each bot can play: rock_paper_scissors (1), rock_paper_scissors_lizard_spock (2) or prisoners_dilemma (3)
+ALTER TABLE bots ADD games INT[] NOT NULL DEFAULT '{1}';
+
+-- The builtin bots' plays are valid in rock-paper-scissors-lizard-Spock too, and a couple of
+-- builtins for the prisoner's dilemma
+UPDATE bots SET games = '{1,2}' WHERE is_builtin = true;
+INSERT INTO bots (name, run_type, script_contents, wasm_path, is_builtin, games) VALUES ('Tit for Tat', 2, E'import json\ninp = json.loads(input())\nprint(inp[''opponent_history''][-1].lower() if inp[''opponent_history''] else ''cooperate'')', '', true, '{3}');
+INSERT INTO bots (name, run_type, script_contents, wasm_path, is_builtin, games) VALUES ('Backstabber', 2, 'print(''defect'')', '', true, '{3}');
@@ -0,0 +1,99 @@
+use anyhow::{anyhow, Result};
+use std::env;
+use std::fs;
+use std::io::{self, Cursor, Read};
+use std::path::{Path, PathBuf};
+use zip::ZipArchive;
+
+/// Most files a bot's zip archive may contain, including directories.
+pub const MAX_ARCHIVE_FILES: usize = 200;
+/// Most bytes a bot's zip archive may contain once extracted.
+pub const MAX_ARCHIVE_SIZE: u64 = 10 * 1024 * 1024; // 10MiB
+
+/// Checks that an uploaded archive is within the limits and has the interpreter's entry
+/// point at the top level. Errors are messages that can be shown to the bot's author.
+pub fn check_archive(bytes: &[u8], entry_point: &String) -> std::result::Result<(), String> {
+    let mut archive = ZipArchive::new(Cursor::new(bytes))
+        .map_err(|_| "File is not a zip archive.".to_string())?;
+    if archive.len() > MAX_ARCHIVE_FILES {
+        return Err(format!(
+            "Archives are limited to {} files.",
+            MAX_ARCHIVE_FILES
+        ));
+    }
+
+    let mut total_size = 0;
+    let mut has_entry_point = false;
+    for i in 0..archive.len() {
+        let file = archive
+            .by_index(i)
+            .map_err(|e| format!("Could not read the archive. Error: {}", e))?;
+        let name = match file.enclosed_name() {
+            Some(name) => name,
+            None => return Err(format!("Archive contains an invalid path: {}", file.name())),
+        };
+        total_size += file.size();
+        has_entry_point |= file.is_file() && name == Path::new(entry_point);
+    }
+    if total_size > MAX_ARCHIVE_SIZE {
+        return Err(format!(
+            "Archives are limited to {}MiB once extracted.",
+            MAX_ARCHIVE_SIZE / 1024 / 1024
+        ));
+    }
+    if !has_entry_point {
+        return Err(format!(
+            "Archive must contain {} at the top level.",
+            entry_point
+        ));
+    }
+    return Ok(());
+}
+
+/// Extracts a bot's archive, keyed by its hash, and returns the directory it's in. Archives
+/// are only extracted once, and the directory is shared by every run of the bot.
+pub fn extract_archive(bytes: &[u8], hash: &String) -> Result<PathBuf> {
+    let archives_dir = env::temp_dir().join("snippy-bot-archives");
+    let archive_dir = archives_dir.join(hash);
+    if archive_dir.exists() {
+        return Ok(archive_dir);
+    }
+
+    // Extract somewhere private first, so a bot never sees a half-extracted archive if
+    // another run of it is extracting it at the same time.
+    let staging_dir = archives_dir.join(format!("{}-{:016x}", hash, rand::random::<u64>()));
+    fs::create_dir_all(&staging_dir)?;
+    if let Err(e) = extract_into(bytes, &staging_dir) {
+        fs::remove_dir_all(&staging_dir)?;
+        return Err(e);
+    }
+    if let Err(e) = fs::rename(&staging_dir, &archive_dir) {
+        fs::remove_dir_all(&staging_dir)?;
+        if !archive_dir.exists() {
+            return Err(e.into());
+        }
+    }
+    return Ok(archive_dir);
+}
+
+fn extract_into(bytes: &[u8], dir: &Path) -> Result<()> {
+    let mut archive = ZipArchive::new(Cursor::new(bytes))?;
+    for i in 0..archive.len().min(MAX_ARCHIVE_FILES) {
+        let file = archive.by_index(i)?;
+        let path = match file.enclosed_name() {
+            Some(name) => dir.join(name),
+            None => return Err(anyhow!("Invalid path in archive: {}", file.name())),
+        };
+        if file.is_dir() {
+            fs::create_dir_all(&path)?;
+            continue;
+        }
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        // Sizes were checked on upload, but don't trust the headers to match the contents.
+        let size = file.size();
+        io::copy(&mut file.take(size), &mut fs::File::create(&path)?)?;
+    }
+    return Ok(());
+}
@@ -0,0 +1,208 @@
+use anyhow::Result;
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use wasi_preview1_component_adapter_provider::WASI_SNAPSHOT_PREVIEW1_ADAPTER_NAME;
+use wasi_preview1_component_adapter_provider::WASI_SNAPSHOT_PREVIEW1_COMMAND_ADAPTER;
+use wasmtime::component::{Component, InstancePre, Linker};
+use wasmtime::Engine;
+
+use crate::tournament::ComponentRunStates;
+
+/// Most components kept in memory. The least recently used one is dropped to make room, and
+/// is loaded from disk again if it's needed.
+const MAX_CACHED_COMPONENTS: usize = 200;
+
+/// Compiled bot components, keyed by the sha256 of the uploaded Wasm module.
+///
+/// Components are kept in memory, ready to instantiate, and their compiled artifacts are
+/// written to disk so a restarted server doesn't have to compile them again. Artifacts are
+/// loaded as native code, so the directory has to be private to the server's user.
+pub struct ComponentCache {
+    /// Not set if the directory couldn't be made private, in which case nothing is written
+    /// to or loaded from disk.
+    cache_dir: Option<PathBuf>,
+    /// Each component with when it was last used, counted in `uses`.
+    instances: Mutex<HashMap<String, (InstancePre<ComponentRunStates>, u64)>>,
+    uses: Mutex<u64>,
+}
+
+impl ComponentCache {
+    pub fn new(cache_dir: PathBuf) -> ComponentCache {
+        let cache_dir = match create_private_dir(&cache_dir) {
+            Ok(()) => Some(cache_dir),
+            Err(e) => {
+                println!(
+                    "Could not create a private component cache directory {}, compiled components won't be saved: {}",
+                    cache_dir.display(),
+                    e
+                );
+                None
+            }
+        };
+        ComponentCache {
+            cache_dir,
+            instances: Mutex::new(HashMap::new()),
+            uses: Mutex::new(0),
+        }
+    }
+
+    /// Returns the pre-instantiated component for a WASI preview1 module, compiling it
+    /// only if it isn't in memory or on disk already.
+    pub fn get_or_load(
+        &self,
+        engine: &Engine,
+        linker: &Linker<ComponentRunStates>,
+        hash: &String,
+        bytes: &[u8],
+    ) -> Result<InstancePre<ComponentRunStates>> {
+        return self.get_or_build(engine, linker, hash, || Ok(bytes.to_vec()));
+    }
+
+    /// Like `get_or_load`, but the module is only built when nothing is cached under `hash`.
+    /// Used for modules that are expensive to produce, like the Python interpreter snapshot.
+    pub fn get_or_build(
+        &self,
+        engine: &Engine,
+        linker: &Linker<ComponentRunStates>,
+        hash: &String,
+        build_module: impl FnOnce() -> Result<Vec<u8>>,
+    ) -> Result<InstancePre<ComponentRunStates>> {
+        return self.get_or_compile(engine, linker, hash, || {
+            load_wasi_preview1_module_as_component(engine, &build_module()?)
+        });
+    }
+
+    /// Returns the pre-instantiated component for bytes that are already a component.
+    pub fn get_or_load_component(
+        &self,
+        engine: &Engine,
+        linker: &Linker<ComponentRunStates>,
+        hash: &String,
+        bytes: &[u8],
+    ) -> Result<InstancePre<ComponentRunStates>> {
+        return self.get_or_compile(engine, linker, hash, || {
+            Component::from_binary(engine, bytes)
+        });
+    }
+
+    fn get_or_compile(
+        &self,
+        engine: &Engine,
+        linker: &Linker<ComponentRunStates>,
+        hash: &String,
+        compile: impl FnOnce() -> Result<Component>,
+    ) -> Result<InstancePre<ComponentRunStates>> {
+        let used_at = {
+            let mut uses = self.uses.lock().unwrap();
+            *uses += 1;
+            *uses
+        };
+        if let Some((instance_pre, last_used)) = self.instances.lock().unwrap().get_mut(hash) {
+            *last_used = used_at;
+            return Ok(instance_pre.clone());
+        }
+
+        let component = self.load_component(engine, hash, compile)?;
+        let instance_pre = linker.instantiate_pre(&component)?;
+        let mut instances = self.instances.lock().unwrap();
+        if instances.len() >= MAX_CACHED_COMPONENTS {
+            let least_recently_used = instances
+                .iter()
+                .min_by_key(|(_, (_, last_used))| *last_used)
+                .map(|(hash, _)| hash.clone());
+            if let Some(least_recently_used) = least_recently_used {
+                instances.remove(&least_recently_used);
+            }
+        }
+        instances.insert(hash.clone(), (instance_pre.clone(), used_at));
+        return Ok(instance_pre);
+    }
+
+    fn load_component(
+        &self,
+        engine: &Engine,
+        hash: &String,
+        compile: impl FnOnce() -> Result<Component>,
+    ) -> Result<Component> {
+        let cache_dir = match &self.cache_dir {
+            Some(cache_dir) => cache_dir,
+            None => return compile(),
+        };
+        let artifact_path = cache_dir.join(format!("{}.cwasm", hash));
+        if artifact_path.exists() {
+            // Safety: the artifact was written by `Component::serialize` in this directory,
+            // which only the server's user can write to, and renamed into place once it was
+            // complete. Wasmtime rejects artifacts built by a different version or engine
+            // config.
+            match unsafe { Component::deserialize_file(engine, &artifact_path) } {
+                Ok(component) => return Ok(component),
+                Err(e) => println!(
+                    "Could not load compiled component {}, recompiling: {}",
+                    artifact_path.display(),
+                    e
+                ),
+            }
+        }
+
+        let component = compile()?;
+        match component.serialize() {
+            Ok(artifact) => {
+                if let Err(e) = write_atomically(&artifact_path, &artifact) {
+                    println!(
+                        "Could not save compiled component {}: {}",
+                        artifact_path.display(),
+                        e
+                    );
+                }
+            }
+            Err(e) => println!("Could not serialize compiled component: {}", e),
+        }
+        return Ok(component);
+    }
+}
+
+/// Creates the directory if needed, and makes it readable and writable only by the server's
+/// user. Changing the permissions fails if the directory belongs to someone else.
+fn create_private_dir(dir: &Path) -> io::Result<()> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::{DirBuilderExt, PermissionsExt};
+        fs::DirBuilder::new()
+            .recursive(true)
+            .mode(0o700)
+            .create(dir)?;
+        fs::set_permissions(dir, fs::Permissions::from_mode(0o700))?;
+    }
+    #[cfg(not(unix))]
+    fs::create_dir_all(dir)?;
+    return Ok(());
+}
+
+/// Writes to a temporary file next to `path` and renames it into place, so a crash or
+/// another server writing the same artifact never leaves a partly written one at `path`.
+fn write_atomically(path: &Path, contents: &[u8]) -> io::Result<()> {
+    let temp_path = path.with_extension(format!("tmp-{:016x}", rand::random::<u64>()));
+    if let Err(e) = fs::write(&temp_path, contents) {
+        let _ = fs::remove_file(&temp_path);
+        return Err(e);
+    }
+    if let Err(e) = fs::rename(&temp_path, path) {
+        let _ = fs::remove_file(&temp_path);
+        return Err(e);
+    }
+    return Ok(());
+}
+
+fn load_wasi_preview1_module_as_component(engine: &Engine, bytes: &[u8]) -> Result<Component> {
+    let component_bytes = wit_component::ComponentEncoder::default()
+        .module(bytes)?
+        .adapter(
+            WASI_SNAPSHOT_PREVIEW1_ADAPTER_NAME,
+            WASI_SNAPSHOT_PREVIEW1_COMMAND_ADAPTER,
+        )?
+        .encode()?;
+    return Component::from_binary(engine, &component_bytes);
+}
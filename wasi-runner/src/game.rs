@@ -0,0 +1,129 @@
+use anyhow::{anyhow, Result};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::fmt::Debug;
+use std::str::FromStr;
+
+use crate::tournament::{BotRunInput, RoundResult};
+
+/// The games a tournament can be played in. Bots are told which one in their input.
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+pub enum GameKind {
+    #[default]
+    RockPaperScissors,
+    RockPaperScissorsLizardSpock,
+    PrisonersDilemma,
+}
+
+impl FromStr for GameKind {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<GameKind> {
+        match s {
+            "rock_paper_scissors" => Ok(GameKind::RockPaperScissors),
+            "rock_paper_scissors_lizard_spock" => Ok(GameKind::RockPaperScissorsLizardSpock),
+            "prisoners_dilemma" => Ok(GameKind::PrisonersDilemma),
+            _ => Err(anyhow!("Unknown game: {}", s)),
+        }
+    }
+}
+
+impl GameKind {
+    pub fn to_db_value(&self) -> i32 {
+        match self {
+            GameKind::RockPaperScissors => 1,
+            GameKind::RockPaperScissorsLizardSpock => 2,
+            GameKind::PrisonersDilemma => 3,
+        }
+    }
+
+    pub fn from_db_value(value: i32) -> GameKind {
+        match value {
+            2 => GameKind::RockPaperScissorsLizardSpock,
+            3 => GameKind::PrisonersDilemma,
+            _ => GameKind::RockPaperScissors,
+        }
+    }
+}
+
+/// Evaluates `$body` with `$game` set to the game chosen in a tournament config. The body is
+/// compiled separately for each game, so it can use the game's own move type.
+macro_rules! with_game {
+    ($config:expr, $game:ident => $body:expr) => {
+        match $config.game {
+            $crate::game::GameKind::RockPaperScissors => {
+                let $game = $crate::rps::RockPaperScissors;
+                $body
+            }
+            $crate::game::GameKind::RockPaperScissorsLizardSpock => {
+                let $game = $crate::rpsls::RockPaperScissorsLizardSpock;
+                $body
+            }
+            $crate::game::GameKind::PrisonersDilemma => {
+                let $game = $config.prisoners_dilemma.clone();
+                $body
+            }
+        }
+    };
+}
+pub(crate) use with_game;
+
+/// The rules of a game that bots play against each other. A match is played in rounds: both
+/// bots choose a move at the same time and score the payoff for that pair of moves.
+pub trait Game: Clone + Send + Sync + 'static {
+    /// A bot's move. Every game has an invalid move, which is what bots that didn't play a
+    /// valid move are recorded as playing.
+    type Move: Clone + Debug + PartialEq + Serialize + DeserializeOwned + Send + Sync + 'static;
+
+    fn kind(&self) -> GameKind;
+
+    /// Every valid move.
+    fn moves(&self) -> Vec<Self::Move>;
+
+    fn invalid_move(&self) -> Self::Move;
+
+    /// Reads a move from a line of a bot's output. Anything that isn't a valid move is the
+    /// invalid move.
+    fn parse_move(&self, line: &str) -> Self::Move;
+
+    fn move_to_db_value(&self, play: &Self::Move) -> i32;
+
+    fn move_from_db_value(&self, value: i32) -> Self::Move;
+
+    /// Points scored in a round by a bot that played `play` and by its opponent.
+    fn payoff(&self, play: &Self::Move, opponent_play: &Self::Move) -> (u32, u32);
+
+    /// Number of rounds in a match, unless it's decided early. Games can vary it from match
+    /// to match with the match's seed.
+    fn rounds(&self, seed: u64) -> u32;
+
+    /// Fewest and most rounds `rounds` can give.
+    fn round_range(&self) -> (u32, u32);
+
+    /// Score that wins a match of `rounds` rounds as soon as a bot reaches it, if there is
+    /// one. Otherwise every round is played.
+    fn win_threshold(&self, rounds: u32) -> Option<u32>;
+
+    /// Whether bots are ranked by the total payoff of all their matches rather than by
+    /// the matches they won.
+    fn ranks_by_payoff(&self) -> bool {
+        false
+    }
+
+    /// The input a bot gets for its next move. By default it's sent as JSON.
+    fn bot_input(&self, input: &BotRunInput<Self::Move>) -> String {
+        serde_json::to_string(input).unwrap()
+    }
+
+    /// How a round went for the bot that played `play`.
+    fn round_result(&self, play: &Self::Move, opponent_play: &Self::Move) -> RoundResult {
+        let (points, opponent_points) = self.payoff(play, opponent_play);
+        if points > opponent_points {
+            RoundResult::Win
+        } else if opponent_points > points {
+            RoundResult::Loss
+        } else {
+            RoundResult::Draw
+        }
+    }
+}
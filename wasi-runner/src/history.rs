@@ -0,0 +1,467 @@
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use crate::game::{with_game, Game};
+use crate::standings;
+use crate::tournament::{
+    BotDetails, BotRunType, Bracket, GameTournament, Match, MatchOutcome, MatchState,
+    MoveAnnotation, ParticipantOutcome, Tournament, TournamentConfig, TournamentFormat, WasmKind,
+};
+use crate::ConnectionPool;
+
+const MAX_PAGE_SIZE: i64 = 100;
+const DEFAULT_PAGE_SIZE: i64 = 25;
+
+#[derive(Deserialize)]
+pub struct TournamentListQuery {
+    pub page: Option<i64>,
+    pub page_size: Option<i64>,
+}
+
+#[derive(Serialize)]
+pub struct TournamentSummary {
+    pub id: i32,
+    pub started_at: String,
+    pub finished_at: Option<String>,
+    pub winner: Option<String>,
+    pub num_matches: i64,
+}
+
+#[derive(Serialize)]
+pub struct TournamentList {
+    pub tournaments: Vec<TournamentSummary>,
+    pub total: i64,
+    pub page: i64,
+    pub page_size: i64,
+}
+
+/// Stores a newly created tournament and its bracket. Sets the database ids on the
+/// tournament so that match results can be recorded against them as they come in.
+pub async fn save_tournament<G: Game>(
+    db_pool: &ConnectionPool,
+    tournament: &mut Tournament<G>,
+) -> Result<()> {
+    let conn = db_pool.get().await?;
+    let tournament_id: i32 = conn
+        .query_one(
+            "INSERT INTO tournaments (format, config) VALUES ($1, $2) RETURNING id",
+            &[
+                &tournament.config.format.to_db_value(),
+                &serde_json::to_string(&tournament.config)?,
+            ],
+        )
+        .await?
+        .get(0);
+
+    tournament.id = Some(tournament_id);
+    tournament.match_db_ids =
+        save_matches(db_pool, tournament.id, &tournament.starting_matches, 0).await?;
+    return Ok(());
+}
+
+/// Stores matches added to a tournament's bracket, starting at the given position in
+/// bracket order. Returns the database id of each match, keyed by match id.
+pub async fn save_matches(
+    db_pool: &ConnectionPool,
+    tournament_id: Option<i32>,
+    matches: &[Match],
+    first_order: usize,
+) -> Result<HashMap<String, i32>> {
+    let mut match_db_ids: HashMap<String, i32> = HashMap::new();
+    let tournament_id = match tournament_id {
+        Some(tournament_id) => tournament_id,
+        None => return Ok(match_db_ids),
+    };
+    let mut conn = db_pool.get().await?;
+    let transaction = conn.transaction().await?;
+
+    let match_stmt = transaction
+        .prepare("INSERT INTO tournament_matches (tournament_id, match_key, match_order, tournament_round_text, next_match_key, next_loser_match_key, bracket, state) VALUES ($1, $2, $3, $4, $5, $6, $7, $8) RETURNING id")
+        .await?;
+    let participant_stmt = transaction
+        .prepare("INSERT INTO match_participants (match_id, position, bot_id, bot_name, is_starting) VALUES ($1, $2, $3, $4, true)")
+        .await?;
+
+    for (order, this_match) in matches.iter().enumerate() {
+        let match_db_id: i32 = transaction
+            .query_one(
+                &match_stmt,
+                &[
+                    &tournament_id,
+                    &this_match.id,
+                    &((first_order + order) as i32),
+                    &this_match.tournament_round_text,
+                    &this_match.next_match_id,
+                    &this_match.next_loser_match_id,
+                    &this_match.bracket.to_db_value(),
+                    &this_match.state.to_db_value(),
+                ],
+            )
+            .await?
+            .get(0);
+        for (position, bot) in this_match.participants.iter().enumerate() {
+            transaction
+                .execute(
+                    &participant_stmt,
+                    &[&match_db_id, &(position as i32), &bot.id, &bot.name],
+                )
+                .await?;
+        }
+        match_db_ids.insert(this_match.id.clone(), match_db_id);
+    }
+    transaction.commit().await?;
+    return Ok(match_db_ids);
+}
+
+/// Records the moves played in one round of a match.
+pub async fn save_round<G: Game>(
+    game: &G,
+    db_pool: &ConnectionPool,
+    match_db_id: Option<i32>,
+    round: u32,
+    plays: &[&G::Move],
+    fuel: &[Option<u64>],
+    annotations: &[&Option<MoveAnnotation>],
+) -> Result<()> {
+    let match_db_id = match match_db_id {
+        Some(match_db_id) => match_db_id,
+        None => return Ok(()),
+    };
+    let conn = db_pool.get().await?;
+    let stmt = conn
+        .prepare("INSERT INTO match_moves (match_id, round, position, play, fuel, confidence, note) VALUES ($1, $2, $3, $4, $5, $6, $7) ON CONFLICT DO NOTHING")
+        .await?;
+    for (position, ((play, fuel), annotation)) in
+        plays.iter().zip(fuel).zip(annotations).enumerate()
+    {
+        let confidence = annotation.as_ref().and_then(|a| a.confidence);
+        let note = annotation.as_ref().and_then(|a| a.note.clone());
+        conn.execute(
+            &stmt,
+            &[
+                &match_db_id,
+                &(round as i32),
+                &(position as i32),
+                &game.move_to_db_value(play),
+                &fuel.map(|fuel| fuel as i64),
+                &confidence,
+                &note,
+            ],
+        )
+        .await?;
+    }
+    return Ok(());
+}
+
+/// Records the final state of a match, including the bots that played in it.
+pub async fn save_match_outcome<M>(
+    db_pool: &ConnectionPool,
+    match_db_id: Option<i32>,
+    participants: &[BotDetails],
+    outcome: &MatchOutcome<M>,
+) -> Result<()> {
+    let match_db_id = match match_db_id {
+        Some(match_db_id) => match_db_id,
+        None => return Ok(()),
+    };
+    let mut conn = db_pool.get().await?;
+    let transaction = conn.transaction().await?;
+    transaction
+        .execute(
+            "UPDATE tournament_matches SET state = $2, winner = $3, is_draw = $4, note = $5 WHERE id = $1",
+            &[
+                &match_db_id,
+                &outcome.state.to_db_value(),
+                &(outcome.winner as i32),
+                &outcome.draw,
+                &outcome.note,
+            ],
+        )
+        .await?;
+    let stmt = transaction
+        .prepare("INSERT INTO match_participants (match_id, position, bot_id, bot_name, is_starting, is_winner) VALUES ($1, $2, $3, $4, false, $5) ON CONFLICT (match_id, position) DO UPDATE SET is_winner = EXCLUDED.is_winner")
+        .await?;
+    for (position, (bot, participant)) in participants
+        .iter()
+        .zip(outcome.participants.iter())
+        .enumerate()
+    {
+        transaction
+            .execute(
+                &stmt,
+                &[
+                    &match_db_id,
+                    &(position as i32),
+                    &bot.id,
+                    &bot.name,
+                    &participant.winner,
+                ],
+            )
+            .await?;
+    }
+    transaction.commit().await?;
+    return Ok(());
+}
+
+pub async fn finish_tournament(
+    db_pool: &ConnectionPool,
+    tournament_id: Option<i32>,
+    winner_bot_id: Option<i32>,
+) -> Result<u64> {
+    let tournament_id = match tournament_id {
+        Some(tournament_id) => tournament_id,
+        None => return Ok(0),
+    };
+    let conn = db_pool.get().await?;
+    let stmt = conn
+        .prepare("UPDATE tournaments SET finished_at = NOW(), winner_bot_id = $2 WHERE id = $1")
+        .await?;
+    let count = conn
+        .execute(&stmt, &[&tournament_id, &winner_bot_id])
+        .await?;
+    return Ok(count);
+}
+
+pub async fn list_tournaments(
+    db_pool: &ConnectionPool,
+    query: &TournamentListQuery,
+) -> Result<TournamentList> {
+    let page = query.page.unwrap_or(1).max(1);
+    let page_size = query
+        .page_size
+        .unwrap_or(DEFAULT_PAGE_SIZE)
+        .clamp(1, MAX_PAGE_SIZE);
+    let offset = (page - 1) * page_size;
+
+    let conn = db_pool.get().await?;
+    let total: i64 = conn
+        .query_one("SELECT COUNT(*) FROM tournaments", &[])
+        .await?
+        .get(0);
+    let rows = conn
+        .query(
+            "SELECT t.id, t.started_at::TEXT, t.finished_at::TEXT, b.name, (SELECT COUNT(*) FROM tournament_matches m WHERE m.tournament_id = t.id) FROM tournaments t LEFT JOIN bots b ON b.id = t.winner_bot_id ORDER BY t.id DESC LIMIT $1 OFFSET $2",
+            &[&page_size, &offset],
+        )
+        .await?;
+    let tournaments = rows
+        .iter()
+        .map(|row| TournamentSummary {
+            id: row.get(0),
+            started_at: row.get(1),
+            finished_at: row.get(2),
+            winner: row.get(3),
+            num_matches: row.get(4),
+        })
+        .collect();
+
+    return Ok(TournamentList {
+        tournaments,
+        total,
+        page,
+        page_size,
+    });
+}
+
+/// The config a stored tournament was played with. Tournaments from before configs were
+/// saved only have their format.
+async fn get_tournament_config(
+    db_pool: &ConnectionPool,
+    tournament_id: i32,
+) -> Result<Option<TournamentConfig>> {
+    let conn = db_pool.get().await?;
+    let tournament_row = match conn
+        .query_opt(
+            "SELECT format, config FROM tournaments WHERE id = $1",
+            &[&tournament_id],
+        )
+        .await?
+    {
+        Some(row) => row,
+        None => return Ok(None),
+    };
+    let config_json: Option<String> = tournament_row.get(1);
+    let config = match config_json.and_then(|json| serde_json::from_str(&json).ok()) {
+        Some(config) => config,
+        None => TournamentConfig {
+            format: TournamentFormat::from_db_value(tournament_row.get(0)),
+            ..Default::default()
+        },
+    };
+    return Ok(Some(config));
+}
+
+/// The config to play a stored tournament again: the same settings and seed, with the bots
+/// that played in it. Bots that have been deleted since can't be brought back.
+pub async fn get_rerun_config(
+    db_pool: &ConnectionPool,
+    tournament_id: i32,
+) -> Result<Option<TournamentConfig>> {
+    let mut config = match get_tournament_config(db_pool, tournament_id).await? {
+        Some(config) => config,
+        None => return Ok(None),
+    };
+    let conn = db_pool.get().await?;
+    let bot_rows = conn
+        .query(
+            "SELECT DISTINCT p.bot_id FROM match_participants p JOIN tournament_matches m ON m.id = p.match_id WHERE m.tournament_id = $1 AND p.bot_id IS NOT NULL ORDER BY p.bot_id",
+            &[&tournament_id],
+        )
+        .await?;
+    config.bot_ids = Some(bot_rows.iter().map(|row| row.get(0)).collect());
+    return Ok(Some(config));
+}
+
+/// Rebuilds a stored tournament in the same shape as the live websocket payload.
+pub async fn get_tournament(
+    db_pool: &ConnectionPool,
+    tournament_id: i32,
+) -> Result<Option<GameTournament>> {
+    let config = match get_tournament_config(db_pool, tournament_id).await? {
+        Some(config) => config,
+        None => return Ok(None),
+    };
+
+    let tournament = with_game!(config, game => {
+        GameTournament::from(load_tournament(game, db_pool, tournament_id, config).await?)
+    });
+    return Ok(Some(tournament));
+}
+
+/// A bot's plays in one match, with the fuel used and its annotation for each.
+type MoveRows<M> = (Vec<M>, Vec<Option<u64>>, Vec<Option<MoveAnnotation>>);
+
+/// Loads the matches of a stored tournament that's played in the given game.
+async fn load_tournament<G: Game>(
+    game: G,
+    db_pool: &ConnectionPool,
+    tournament_id: i32,
+    config: TournamentConfig,
+) -> Result<Tournament<G>> {
+    let conn = db_pool.get().await?;
+    let match_rows = conn
+        .query(
+            "SELECT id, match_key, tournament_round_text, next_match_key, state, winner, note, is_draw, next_loser_match_key, bracket FROM tournament_matches WHERE tournament_id = $1 ORDER BY match_order",
+            &[&tournament_id],
+        )
+        .await?;
+    let participant_rows = conn
+        .query(
+            "SELECT p.match_id, p.bot_id, p.bot_name, p.is_starting, p.is_winner, COALESCE(b.run_type, 2) FROM match_participants p JOIN tournament_matches m ON m.id = p.match_id LEFT JOIN bots b ON b.id = p.bot_id WHERE m.tournament_id = $1 ORDER BY p.match_id, p.position",
+            &[&tournament_id],
+        )
+        .await?;
+    let move_rows = conn
+        .query(
+            "SELECT mv.match_id, mv.position, mv.play, mv.fuel, mv.confidence, mv.note FROM match_moves mv JOIN tournament_matches m ON m.id = mv.match_id WHERE m.tournament_id = $1 ORDER BY mv.match_id, mv.round",
+            &[&tournament_id],
+        )
+        .await?;
+
+    let mut moves: HashMap<(i32, i32), MoveRows<G::Move>> = HashMap::new();
+    for row in &move_rows {
+        let match_db_id: i32 = row.get(0);
+        let position: i32 = row.get(1);
+        let fuel: Option<i64> = row.get(3);
+        let confidence: Option<f32> = row.get(4);
+        let note: Option<String> = row.get(5);
+        let (plays, fuel_used, annotations) = moves.entry((match_db_id, position)).or_default();
+        plays.push(game.move_from_db_value(row.get(2)));
+        fuel_used.push(fuel.map(|fuel| fuel as u64));
+        annotations.push(if confidence.is_some() || note.is_some() {
+            Some(MoveAnnotation { confidence, note })
+        } else {
+            None
+        });
+    }
+
+    let mut starting_matches: Vec<Match> = vec![];
+    let mut match_updates: Vec<MatchOutcome<G::Move>> = vec![];
+    let mut match_db_ids: HashMap<String, i32> = HashMap::new();
+    for row in &match_rows {
+        let match_db_id: i32 = row.get(0);
+        let match_key: String = row.get(1);
+        let state = MatchState::from_db_value(row.get(4));
+        let winner: Option<i32> = row.get(5);
+        let participants: Vec<_> = participant_rows
+            .iter()
+            .filter(|p| p.get::<_, i32>(0) == match_db_id)
+            .collect();
+
+        let starting_participants = participants
+            .iter()
+            .filter(|p| p.get::<_, bool>(3))
+            .map(|p| BotDetails {
+                id: p.get(1),
+                run_type: BotRunType::from_db_value(p.get(5)),
+                name: p.get(2),
+                code: "".to_string(),
+                wasm_path: "".to_string(),
+                wasm_bytes: None,
+                wasm_kind: WasmKind::CoreModule,
+                session: false,
+                language: "".to_string(),
+                games: vec![],
+            })
+            .collect();
+        starting_matches.push(Match {
+            id: match_key.clone(),
+            tournament_round_text: row.get(2),
+            next_match_id: row.get(3),
+            next_loser_match_id: row.get(8),
+            bracket: Bracket::from_db_value(row.get(9)),
+            participants: starting_participants,
+            state: match state {
+                MatchState::Bye => MatchState::Bye,
+                _ => MatchState::NotStarted,
+            },
+        });
+
+        if state == MatchState::Bye || state == MatchState::Finished {
+            let participant_outcomes = participants
+                .iter()
+                .enumerate()
+                .map(|(position, p)| {
+                    let (moves, fuel, annotations) = moves
+                        .remove(&(match_db_id, position as i32))
+                        .unwrap_or_default();
+                    ParticipantOutcome {
+                        name: p.get(2),
+                        moves,
+                        fuel,
+                        annotations,
+                        winner: p.get(4),
+                    }
+                })
+                .collect();
+            match_updates.push(MatchOutcome {
+                match_id: match_key.clone(),
+                state,
+                note: row.get(6),
+                winner: winner.unwrap_or(0) as usize,
+                draw: row.get(7),
+                participants: participant_outcomes,
+            });
+        }
+        match_db_ids.insert(match_key, match_db_id);
+    }
+
+    let mut tournament = Tournament {
+        game,
+        id: Some(tournament_id),
+        config,
+        starting_matches,
+        match_updates,
+        standings: vec![],
+        match_db_ids,
+    };
+    if tournament.keeps_standings() {
+        tournament.standings = standings::compute_standings(
+            &tournament.game,
+            &tournament.bot_names(),
+            &tournament.match_updates,
+        );
+    }
+    return Ok(tournament);
+}
@@ -0,0 +1,167 @@
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use wasmtime::component::{InstancePre, Linker};
+use wasmtime::Engine;
+
+use crate::component_cache::ComponentCache;
+use crate::python_snapshot;
+use crate::tournament::ComponentRunStates;
+
+/// Used when the interpreters config file can't be read: Python and JavaScript, as shipped.
+const DEFAULT_INTERPRETERS_CONFIG: &str = include_str!("../interpreters.json");
+/// Placeholder in an interpreter's args that's replaced with the path to the bot's source file.
+const SOURCE_PLACEHOLDER: &str = "{source}";
+
+/// How to run bots written in one language, as read from the interpreters config file.
+#[derive(Clone, Debug, Deserialize)]
+pub struct InterpreterConfig {
+    /// Shown in the editor's language dropdown, and stored with the bots written in it.
+    pub name: String,
+    /// A WASI preview1 build of the interpreter.
+    pub wasm_path: PathBuf,
+    /// Command line for the interpreter, starting with the program name. `{source}` is
+    /// replaced with the path to the bot's source file.
+    pub args: Vec<String>,
+    pub source_filename: String,
+    /// Where the bot's own directory, holding its source file, is mounted.
+    #[serde(default = "default_source_dir")]
+    pub source_dir: String,
+    /// Extra read-only directories, e.g. for interpreters that keep their standard library
+    /// outside the Wasm file.
+    #[serde(default)]
+    pub preopens: Vec<Preopen>,
+    /// Language id for the editor's syntax highlighting.
+    #[serde(default)]
+    pub editor_language: Option<String>,
+    /// Pre-initialize the interpreter with Wizer. The build must export `wizer.initialize`.
+    #[serde(default)]
+    pub snapshot: bool,
+}
+
+fn default_source_dir() -> String {
+    "/".to_string()
+}
+
+/// A host directory that's mounted read-only in the bot's filesystem.
+#[derive(Clone, Debug, Deserialize)]
+pub struct Preopen {
+    pub host_path: PathBuf,
+    pub guest_path: String,
+}
+
+pub struct Interpreter {
+    pub config: InterpreterConfig,
+    pub component: InstancePre<ComponentRunStates>,
+}
+
+impl Interpreter {
+    /// The interpreter's command line for running the given source file.
+    pub fn args(&self) -> Vec<String> {
+        let source_path = Path::new(&self.config.source_dir)
+            .join(&self.config.source_filename)
+            .to_string_lossy()
+            .to_string();
+        return self
+            .config
+            .args
+            .iter()
+            .map(|arg| arg.replace(SOURCE_PLACEHOLDER, &source_path))
+            .collect();
+    }
+}
+
+/// What the client needs to offer a language in the bot editor.
+#[derive(Serialize)]
+pub struct LanguageInfo {
+    pub name: String,
+    pub editor_language: Option<String>,
+    pub source_filename: String,
+}
+
+/// The interpreters that source code bots can be written for. Adding a language is a matter
+/// of dropping in a WASI build of its interpreter and adding it to the config file.
+pub struct InterpreterRegistry {
+    interpreters: Vec<Interpreter>,
+}
+
+impl InterpreterRegistry {
+    /// Loads every interpreter in the config file. Interpreters that can't be loaded are
+    /// left out, so one missing Wasm file doesn't stop the server from starting.
+    pub fn load(
+        engine: &Engine,
+        linker: &Linker<ComponentRunStates>,
+        component_cache: &ComponentCache,
+        config_path: &PathBuf,
+    ) -> Result<InterpreterRegistry> {
+        let config_json = match fs::read_to_string(config_path) {
+            Ok(config_json) => config_json,
+            Err(e) => {
+                println!(
+                    "Could not read {}, using the default interpreters. Error: {}",
+                    config_path.display(),
+                    e
+                );
+                DEFAULT_INTERPRETERS_CONFIG.to_string()
+            }
+        };
+        let configs: Vec<InterpreterConfig> = serde_json::from_str(&config_json)?;
+
+        let mut interpreters = vec![];
+        for config in configs {
+            println!("Pre-loading {} interpreter component...", config.name);
+            match load_interpreter_component(engine, linker, component_cache, &config) {
+                Ok(component) => interpreters.push(Interpreter { config, component }),
+                Err(e) => println!(
+                    "Could not load the {} interpreter from {}, {} bots are disabled. Error: {}",
+                    config.name,
+                    config.wasm_path.display(),
+                    config.name,
+                    e
+                ),
+            }
+        }
+        return Ok(InterpreterRegistry { interpreters });
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Interpreter> {
+        self.interpreters
+            .iter()
+            .find(|interpreter| interpreter.config.name == name)
+    }
+
+    pub fn languages(&self) -> Vec<LanguageInfo> {
+        self.interpreters
+            .iter()
+            .map(|interpreter| LanguageInfo {
+                name: interpreter.config.name.clone(),
+                editor_language: interpreter.config.editor_language.clone(),
+                source_filename: interpreter.config.source_filename.clone(),
+            })
+            .collect()
+    }
+}
+
+fn load_interpreter_component(
+    engine: &Engine,
+    linker: &Linker<ComponentRunStates>,
+    component_cache: &ComponentCache,
+    config: &InterpreterConfig,
+) -> Result<InstancePre<ComponentRunStates>> {
+    let wasm_bytes = fs::read(&config.wasm_path)?;
+    let hash = sha256::digest(&wasm_bytes);
+    if config.snapshot {
+        let snapshot_hash = format!("{}-snapshot", hash);
+        match component_cache.get_or_build(engine, linker, &snapshot_hash, || {
+            python_snapshot::snapshot_python_interpreter(&wasm_bytes)
+        }) {
+            Ok(component) => return Ok(component),
+            Err(e) => println!(
+                "Could not snapshot the {} interpreter, it will start from scratch on every run. Error: {}",
+                config.name, e
+            ),
+        }
+    }
+    return component_cache.get_or_load(engine, linker, &hash, &wasm_bytes);
+}
@@ -0,0 +1,238 @@
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+use crate::tournament::BotRunType;
+use crate::ConnectionPool;
+
+const ELO_K_FACTOR: f64 = 32.0;
+const INITIAL_RATING: f64 = 1000.0;
+const MAX_PAGE_SIZE: i64 = 100;
+const DEFAULT_PAGE_SIZE: i64 = 25;
+
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum LeaderboardSort {
+    Rating,
+    Wins,
+    TournamentsWon,
+    Name,
+    Efficiency,
+}
+
+#[derive(Deserialize)]
+pub struct LeaderboardQuery {
+    pub page: Option<i64>,
+    pub page_size: Option<i64>,
+    pub sort: Option<LeaderboardSort>,
+    pub run_type: Option<BotRunType>,
+}
+
+#[derive(Serialize)]
+pub struct LeaderboardEntry {
+    pub rank: i64,
+    pub name: String,
+    pub run_type: BotRunType,
+    pub rating: f64,
+    pub wins: i32,
+    pub losses: i32,
+    pub draws: i32,
+    pub tournaments_won: i32,
+    pub is_builtin: bool,
+    /// Average fuel used per move, over the moves where it was measured.
+    pub efficiency: Option<f64>,
+}
+
+#[derive(Serialize)]
+pub struct Leaderboard {
+    pub bots: Vec<LeaderboardEntry>,
+    pub total: i64,
+    pub page: i64,
+    pub page_size: i64,
+}
+
+/// The result of a finished match from the point of view of the first bot.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MatchResult {
+    Bot1Won,
+    Bot2Won,
+    Draw,
+}
+
+impl MatchResult {
+    fn bot1_score(&self) -> f64 {
+        match self {
+            MatchResult::Bot1Won => 1.0,
+            MatchResult::Bot2Won => 0.0,
+            MatchResult::Draw => 0.5,
+        }
+    }
+}
+
+/// Returns the new ratings of both bots after a match, using the standard Elo formula.
+fn elo_update(rating1: f64, rating2: f64, result: MatchResult) -> (f64, f64) {
+    let expected1 = 1.0 / (1.0 + 10f64.powf((rating2 - rating1) / 400.0));
+    let score1 = result.bot1_score();
+    let delta = ELO_K_FACTOR * (score1 - expected1);
+    (rating1 + delta, rating2 - delta)
+}
+
+/// Total fuel used by the moves where it was measured, and the number of those moves.
+fn measured_fuel(fuel: &[Option<u64>]) -> (i64, i32) {
+    let measured: Vec<u64> = fuel.iter().flatten().copied().collect();
+    (measured.iter().sum::<u64>() as i64, measured.len() as i32)
+}
+
+/// Updates ratings, win/loss/draw counts and fuel totals for both bots of a finished match.
+/// Bots without a database id (e.g. test runs) are skipped.
+pub async fn record_match_result(
+    db_pool: &ConnectionPool,
+    bot1_id: Option<i32>,
+    bot2_id: Option<i32>,
+    result: MatchResult,
+    fuel: [&[Option<u64>]; 2],
+) -> Result<()> {
+    let (bot1_id, bot2_id) = match (bot1_id, bot2_id) {
+        (Some(bot1_id), Some(bot2_id)) => (bot1_id, bot2_id),
+        _ => return Ok(()),
+    };
+
+    let mut conn = db_pool.get().await?;
+    let transaction = conn.transaction().await?;
+
+    // Lock both rows so concurrent matches can't lose each other's rating updates.
+    let rows = transaction
+        .query(
+            "SELECT id, rating FROM bots WHERE id = $1 OR id = $2 ORDER BY id FOR UPDATE",
+            &[&bot1_id, &bot2_id],
+        )
+        .await?;
+    let rating_of = |id: i32| -> f64 {
+        rows.iter()
+            .find(|row| row.get::<_, i32>(0) == id)
+            .map(|row| row.get(1))
+            .unwrap_or(INITIAL_RATING)
+    };
+    let (new_rating1, new_rating2) = elo_update(rating_of(bot1_id), rating_of(bot2_id), result);
+
+    let stmt = transaction
+        .prepare(
+            "UPDATE bots SET rating = $2, wins = wins + $3, losses = losses + $4, draws = draws + $5, fuel_used = fuel_used + $6, fuel_moves = fuel_moves + $7 WHERE id = $1",
+        )
+        .await?;
+    let (bot1_counts, bot2_counts): ((i32, i32, i32), (i32, i32, i32)) = match result {
+        MatchResult::Bot1Won => ((1, 0, 0), (0, 1, 0)),
+        MatchResult::Bot2Won => ((0, 1, 0), (1, 0, 0)),
+        MatchResult::Draw => ((0, 0, 1), (0, 0, 1)),
+    };
+    let (bot1_fuel, bot1_fuel_moves) = measured_fuel(fuel[0]);
+    let (bot2_fuel, bot2_fuel_moves) = measured_fuel(fuel[1]);
+    transaction
+        .execute(
+            &stmt,
+            &[
+                &bot1_id,
+                &new_rating1,
+                &bot1_counts.0,
+                &bot1_counts.1,
+                &bot1_counts.2,
+                &bot1_fuel,
+                &bot1_fuel_moves,
+            ],
+        )
+        .await?;
+    transaction
+        .execute(
+            &stmt,
+            &[
+                &bot2_id,
+                &new_rating2,
+                &bot2_counts.0,
+                &bot2_counts.1,
+                &bot2_counts.2,
+                &bot2_fuel,
+                &bot2_fuel_moves,
+            ],
+        )
+        .await?;
+    transaction.commit().await?;
+    return Ok(());
+}
+
+pub async fn record_tournament_win(db_pool: &ConnectionPool, bot_id: Option<i32>) -> Result<u64> {
+    let bot_id = match bot_id {
+        Some(bot_id) => bot_id,
+        None => return Ok(0),
+    };
+    let conn = db_pool.get().await?;
+    let stmt = conn
+        .prepare("UPDATE bots SET tournaments_won = tournaments_won + 1 WHERE id = $1")
+        .await?;
+    let count = conn.execute(&stmt, &[&bot_id]).await?;
+    return Ok(count);
+}
+
+pub async fn get_leaderboard(
+    db_pool: &ConnectionPool,
+    query: &LeaderboardQuery,
+) -> Result<Leaderboard> {
+    let page = query.page.unwrap_or(1).max(1);
+    let page_size = query
+        .page_size
+        .unwrap_or(DEFAULT_PAGE_SIZE)
+        .clamp(1, MAX_PAGE_SIZE);
+    let offset = (page - 1) * page_size;
+    let run_type: Option<i32> = query.run_type.as_ref().map(|r| r.to_db_value());
+
+    // The sort column can't be a bind parameter, so it's picked from a fixed list.
+    let order_by = match query.sort.unwrap_or(LeaderboardSort::Rating) {
+        LeaderboardSort::Rating => "rating DESC, wins DESC",
+        LeaderboardSort::Wins => "wins DESC, rating DESC",
+        LeaderboardSort::TournamentsWon => "tournaments_won DESC, rating DESC",
+        LeaderboardSort::Name => "name ASC",
+        LeaderboardSort::Efficiency => "efficiency ASC NULLS LAST, rating DESC",
+    };
+
+    let conn = db_pool.get().await?;
+    let filter =
+        "(is_disabled = false OR is_builtin = true) AND ($1::INT IS NULL OR run_type = $1)";
+    let total: i64 = conn
+        .query_one(
+            &format!("SELECT COUNT(*) FROM bots WHERE {}", filter),
+            &[&run_type],
+        )
+        .await?
+        .get(0);
+    let rows = conn
+        .query(
+            &format!(
+                "SELECT name, run_type, rating, wins, losses, draws, tournaments_won, is_builtin, fuel_used::FLOAT8 / NULLIF(fuel_moves, 0) AS efficiency FROM bots WHERE {} ORDER BY {}, id ASC LIMIT $2 OFFSET $3",
+                filter, order_by
+            ),
+            &[&run_type, &page_size, &offset],
+        )
+        .await?;
+
+    let bots = rows
+        .iter()
+        .enumerate()
+        .map(|(i, row)| LeaderboardEntry {
+            rank: offset + i as i64 + 1,
+            name: row.get(0),
+            run_type: BotRunType::from_db_value(row.get(1)),
+            rating: row.get(2),
+            wins: row.get(3),
+            losses: row.get(4),
+            draws: row.get(5),
+            tournaments_won: row.get(6),
+            is_builtin: row.get(7),
+            efficiency: row.get(8),
+        })
+        .collect();
+
+    return Ok(Leaderboard {
+        bots,
+        total,
+        page,
+        page_size,
+    });
+}
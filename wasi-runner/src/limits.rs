@@ -0,0 +1,195 @@
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+use std::time::Duration;
+use wasmtime::{ResourceLimiter, StoreLimits, StoreLimitsBuilder, Trap};
+
+use crate::tournament::{BotDetails, BotRunType};
+
+/// What a bot gets for a single run, or for each round in session mode.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(default)]
+pub struct BotLimits {
+    /// Wall-clock time, checked on every epoch tick while the bot is running.
+    pub time_ms: u64,
+    /// Number of Wasm instructions.
+    pub fuel: u64,
+    /// Size of each of the bot's linear memories.
+    pub memory_mb: usize,
+    /// Size of stdout and of stderr. Anything written past it is dropped.
+    pub output_kb: usize,
+}
+
+impl Default for BotLimits {
+    fn default() -> BotLimits {
+        BotLimits {
+            time_ms: 1000,
+            fuel: 1_000_000_000,
+            memory_mb: 100,
+            output_kb: 100,
+        }
+    }
+}
+
+impl BotLimits {
+    pub fn time(&self) -> Duration {
+        Duration::from_millis(self.time_ms)
+    }
+
+    pub fn memory_bytes(&self) -> usize {
+        self.memory_mb << 20
+    }
+
+    pub fn output_bytes(&self) -> usize {
+        self.output_kb << 10
+    }
+
+    fn with_overrides(&self, overrides: &LimitOverrides) -> BotLimits {
+        BotLimits {
+            time_ms: overrides.time_ms.unwrap_or(self.time_ms),
+            fuel: overrides.fuel.unwrap_or(self.fuel),
+            memory_mb: overrides.memory_mb.unwrap_or(self.memory_mb),
+            output_kb: overrides.output_kb.unwrap_or(self.output_kb),
+        }
+    }
+}
+
+/// Changes to the default limits for one kind of bot. Limits that aren't set are left as
+/// they are.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct LimitOverrides {
+    pub time_ms: Option<u64>,
+    pub fuel: Option<u64>,
+    pub memory_mb: Option<usize>,
+    pub output_kb: Option<usize>,
+}
+
+/// The limits for every kind of bot. It's part of the tournament config, so each tournament
+/// keeps the limits it was played with.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct LimitsConfig {
+    pub default: BotLimits,
+    /// Keyed by run type (`Wasi` or `Component`), or by language for bots written in one
+    /// of the interpreted languages (e.g. `Python`).
+    pub overrides: HashMap<String, LimitOverrides>,
+}
+
+impl LimitsConfig {
+    pub fn load(path: &PathBuf) -> Result<LimitsConfig> {
+        let config_json = fs::read_to_string(path)?;
+        return Ok(serde_json::from_str(&config_json)?);
+    }
+
+    pub fn for_bot(&self, bot_details: &BotDetails) -> BotLimits {
+        let key = match (bot_details.language(), &bot_details.run_type) {
+            (Some(language), _) => language,
+            (None, BotRunType::Component) => "Component",
+            (None, _) => "Wasi",
+        };
+        match self.overrides.get(key) {
+            Some(overrides) => self.default.with_overrides(overrides),
+            None => self.default.clone(),
+        }
+    }
+}
+
+/// What a bot used during a run, to show bot authors how close they are to the limits.
+#[derive(Clone, Debug, Serialize)]
+pub struct ResourceUsage {
+    pub fuel_consumed: u64,
+    /// Largest size, in bytes, that any of the bot's linear memories grew to.
+    pub peak_memory: usize,
+    /// Seconds spent instantiating the bot before it started running.
+    pub instantiation_time: f32,
+    pub limits: BotLimits,
+}
+
+/// The store's resource limiter, which also keeps track of how large the bot's memory got.
+pub struct BotLimiter {
+    limits: StoreLimits,
+    pub peak_memory: usize,
+}
+
+impl BotLimiter {
+    pub fn new(limits: &BotLimits) -> BotLimiter {
+        BotLimiter {
+            limits: StoreLimitsBuilder::new()
+                .instances(8)
+                .memories(4)
+                .memory_size(limits.memory_bytes())
+                .tables(4)
+                .table_elements(20000)
+                .build(),
+            peak_memory: 0,
+        }
+    }
+}
+
+impl ResourceLimiter for BotLimiter {
+    fn memory_growing(
+        &mut self,
+        current: usize,
+        desired: usize,
+        maximum: Option<usize>,
+    ) -> Result<bool> {
+        let allowed = self.limits.memory_growing(current, desired, maximum)?;
+        if allowed {
+            self.peak_memory = self.peak_memory.max(desired);
+        }
+        return Ok(allowed);
+    }
+
+    fn table_growing(
+        &mut self,
+        current: usize,
+        desired: usize,
+        maximum: Option<usize>,
+    ) -> Result<bool> {
+        self.limits.table_growing(current, desired, maximum)
+    }
+
+    fn instances(&self) -> usize {
+        self.limits.instances()
+    }
+
+    fn tables(&self) -> usize {
+        self.limits.tables()
+    }
+
+    fn memories(&self) -> usize {
+        self.limits.memories()
+    }
+}
+
+/// The limit that stopped a bot, if it was stopped for breaking one.
+#[derive(Debug, PartialEq, Eq)]
+pub enum LimitReached {
+    Time,
+    Fuel,
+}
+
+impl LimitReached {
+    /// The engine stops a bot with an `Interrupt` trap when its epoch deadline callback
+    /// finds it's over time, and an `OutOfFuel` trap when it runs out of fuel.
+    pub fn from_error(error: &anyhow::Error) -> Option<LimitReached> {
+        match error.downcast_ref::<Trap>() {
+            Some(Trap::Interrupt) => Some(LimitReached::Time),
+            Some(Trap::OutOfFuel) => Some(LimitReached::Fuel),
+            _ => None,
+        }
+    }
+
+    pub fn message(&self, limits: &BotLimits) -> String {
+        match self {
+            LimitReached::Time => format!("Timeout! Bots are limited to {}ms", limits.time_ms),
+            LimitReached::Fuel => format!(
+                "Program ran out of fuel: It reached the limit of {} wasm instructions.",
+                limits.fuel
+            ),
+        }
+    }
+}
@@ -1,13 +1,14 @@
+use std::collections::VecDeque;
 use std::env;
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 
 use axum::{
     body::Bytes,
     extract::{
         ws::{Message, WebSocket, WebSocketUpgrade},
-        Multipart, State,
+        Multipart, Path, Query, State,
     },
-    http::StatusCode,
+    http::{header, HeaderMap, StatusCode},
     response::{IntoResponse, Response},
     routing::{get, post},
     Json, Router,
@@ -34,17 +35,44 @@ use futures::{sink::SinkExt, stream::StreamExt};
 use native_tls::{Certificate, TlsConnector};
 use postgres_native_tls::MakeTlsConnector;
 
-use tournament::{BotDetails, BotRunType, SPROption, Tournament};
+use game::{with_game, Game, GameKind};
+use history::TournamentListQuery;
+use leaderboard::LeaderboardQuery;
+use limits::LimitsConfig;
+use rps::RockPaperScissors;
+use tournament::{
+    BotDetails, BotRunType, GameTournament, Tournament, TournamentConfig, WasmKind, WASM_RUNTIME,
+};
 
+mod bot_archive;
+mod component_cache;
+mod game;
+mod history;
+mod interpreters;
+mod leaderboard;
+mod limits;
+mod player;
+mod prisoners_dilemma;
+mod python_snapshot;
+mod rps;
+mod rpsls;
+mod seed;
+mod session;
+mod standings;
 mod tournament;
 
 pub type ConnectionPool = Pool;
 
 struct SharedState {
-    tournament: RwLock<Tournament>,
+    tournament: RwLock<GameTournament>,
     broadcast_channel: broadcast::Sender<String>,
     db_pool: ConnectionPool,
     bucket_name: String,
+    tournament_config: TournamentConfig,
+    /// Tournaments to play before going back to ones with the server's config.
+    queued_tournaments: Mutex<VecDeque<TournamentConfig>>,
+    /// Needed to queue tournaments. They can't be queued if it isn't set.
+    admin_token: Option<String>,
 }
 
 const TOURNAMENT_INTERVAL: u64 = 30;
@@ -65,6 +93,93 @@ async fn main() {
     let db_user = env::var("DB_USER").unwrap_or("snippyuser".to_string());
     let db_password = env::var("DB_PASSWORD").unwrap_or("".to_string());
     let bucket_name = env::var("S3_BUCKET_NAME").expect("S3_BUCKET_NAME is required.");
+    let tournament_format = env::var("TOURNAMENT_FORMAT")
+        .unwrap_or("single_elimination".to_string())
+        .parse()
+        .expect("TOURNAMENT_FORMAT must be one of: single_elimination, double_elimination, round_robin, swiss.");
+    let swiss_rounds: Option<u32> = env::var("SWISS_ROUNDS").ok().map(|rounds| {
+        rounds
+            .parse()
+            .expect("SWISS_ROUNDS must be a valid integer.")
+    });
+    let mut tournament_config = TournamentConfig {
+        format: tournament_format,
+        swiss_rounds,
+        ..Default::default()
+    };
+    if let Ok(game) = env::var("TOURNAMENT_GAME") {
+        tournament_config.game = game
+            .parse()
+            .expect("TOURNAMENT_GAME must be one of: rock_paper_scissors, rock_paper_scissors_lizard_spock, prisoners_dilemma.");
+    }
+    if let Ok(rounds) = env::var("PRISONERS_DILEMMA_ROUNDS") {
+        tournament_config
+            .prisoners_dilemma
+            .set_rounds(&rounds)
+            .expect(
+                "PRISONERS_DILEMMA_ROUNDS must be a number of rounds, or a range like 150-250.",
+            );
+    }
+    if let Ok(payoffs) = env::var("PRISONERS_DILEMMA_PAYOFFS") {
+        tournament_config.prisoners_dilemma.payoffs = payoffs.parse().expect(
+            "PRISONERS_DILEMMA_PAYOFFS must be four integers: temptation,reward,punishment,sucker.",
+        );
+    }
+    if let Ok(rounds) = env::var("MATCH_ROUNDS") {
+        tournament_config.match_rules.rounds = Some(
+            rounds
+                .parse()
+                .expect("MATCH_ROUNDS must be a valid integer."),
+        );
+    }
+    if let Ok(win_threshold) = env::var("MATCH_WIN_THRESHOLD") {
+        tournament_config.match_rules.win_threshold = Some(
+            win_threshold
+                .parse()
+                .expect("MATCH_WIN_THRESHOLD must be a valid integer."),
+        );
+    }
+    if let Ok(ties_count_as_rounds) = env::var("MATCH_TIES_COUNT_AS_ROUNDS") {
+        tournament_config.match_rules.ties_count_as_rounds = ties_count_as_rounds
+            .parse()
+            .expect("MATCH_TIES_COUNT_AS_ROUNDS must be true or false.");
+    }
+    if let Ok(tiebreak) = env::var("TOURNAMENT_TIEBREAK") {
+        tournament_config.tiebreak = tiebreak
+            .parse()
+            .expect("TOURNAMENT_TIEBREAK must be one of: coin_toss, fuel, replay, sudden_death.");
+    }
+    if let Ok(seed) = env::var("TOURNAMENT_SEED") {
+        tournament_config.seed = Some(
+            seed.parse()
+                .expect("TOURNAMENT_SEED must be a valid integer."),
+        );
+    }
+    if let Ok(parallelism) = env::var("TOURNAMENT_PARALLELISM") {
+        tournament_config.parallelism = parallelism
+            .parse()
+            .expect("TOURNAMENT_PARALLELISM must be a valid integer.");
+    }
+    if let Ok(limits_path) = env::var("BOT_LIMITS_CONFIG") {
+        tournament_config.limits = LimitsConfig::load(&PathBuf::from(limits_path))
+            .expect("BOT_LIMITS_CONFIG must be a valid limits config file.");
+    }
+    if let Ok(time_ms) = env::var("BOT_TIME_LIMIT_MS") {
+        tournament_config.limits.default.time_ms = time_ms
+            .parse()
+            .expect("BOT_TIME_LIMIT_MS must be a valid integer.");
+    }
+    if let Ok(fuel) = env::var("BOT_FUEL_LIMIT") {
+        tournament_config.limits.default.fuel = fuel
+            .parse()
+            .expect("BOT_FUEL_LIMIT must be a valid integer.");
+    }
+    if let Ok(memory_mb) = env::var("BOT_MEMORY_LIMIT_MB") {
+        tournament_config.limits.default.memory_mb = memory_mb
+            .parse()
+            .expect("BOT_MEMORY_LIMIT_MB must be a valid integer.");
+    }
+    let admin_token = env::var("ADMIN_TOKEN").ok();
 
     let mut config = Config::new();
     config.host = Some(db_host);
@@ -101,10 +216,13 @@ async fn main() {
 
     let (tx, _rx) = broadcast::channel(200);
     let shared_state: Arc<SharedState> = Arc::new(SharedState {
-        tournament: RwLock::new(Tournament::new()),
+        tournament: RwLock::new(GameTournament::from(Tournament::new(RockPaperScissors))),
         broadcast_channel: tx,
         db_pool,
         bucket_name,
+        tournament_config,
+        queued_tournaments: Mutex::new(VecDeque::new()),
+        admin_token,
     });
 
     // build our application with a route
@@ -116,6 +234,14 @@ async fn main() {
         .route("/health", get(health))
         .route("/api/test", post(test_bot))
         .route("/api/bot", post(post_bot))
+        .route("/api/bots", get(get_bots))
+        .route("/api/languages", get(get_languages))
+        .route(
+            "/api/tournaments",
+            get(list_tournaments).post(queue_tournament),
+        )
+        .route("/api/tournaments/:id", get(get_tournament))
+        .route("/api/tournaments/:id/rerun", post(rerun_tournament))
         .route("/api/upload_wasm", post(upload_wasm))
         .with_state(shared_state.clone())
         .layer(
@@ -154,10 +280,21 @@ async fn start_background_tournaments(shared_state: Arc<SharedState>) -> Result<
 
     while let Some(_ts) = stream.next().await {
         println!("Starting new tournament.");
-        let result =
-            tournament::create_tournament(&shared_state.db_pool, &shared_state.bucket_name).await;
+        let queued_config = shared_state.queued_tournaments.lock().unwrap().pop_front();
+        let config = &queued_config.unwrap_or_else(|| shared_state.tournament_config.clone());
+        let result = with_game!(config, game => {
+            tournament::create_tournament(
+                game,
+                &shared_state.db_pool,
+                &shared_state.bucket_name,
+                config,
+            )
+            .await
+            .map(|tournament| tournament.map(GameTournament::from))
+        });
         match result {
-            Ok(payload) => {
+            Ok(None) => continue,
+            Ok(Some(payload)) => {
                 let mut tournament = shared_state.tournament.write().await;
                 let tournament_json = serde_json::to_string(&payload.clone()).unwrap();
                 shared_state
@@ -197,6 +334,19 @@ struct CreateBotRequest {
     name: String,
     botcode: String,
     run_type: BotRunType,
+    #[serde(default)]
+    session: bool,
+    /// The interpreter for Script bots.
+    #[serde(default)]
+    language: String,
+    /// The games the bot can play.
+    #[serde(default = "default_games")]
+    games: Vec<GameKind>,
+}
+
+/// Bots created before they could say which games they play are rock-paper-scissors bots.
+fn default_games() -> Vec<GameKind> {
+    return vec![GameKind::RockPaperScissors];
 }
 
 async fn post_bot(
@@ -208,11 +358,15 @@ async fn post_bot(
 
     let mut bot: BotDetails = BotDetails {
         id: None,
-        run_type: payload.run_type,
+        run_type: payload.run_type.for_language(&payload.language),
         name: botname.clone(),
         code: botcode.clone(),
         wasm_path: "".to_string(),
         wasm_bytes: None,
+        wasm_kind: WasmKind::CoreModule,
+        session: payload.session,
+        language: payload.language,
+        games: payload.games,
     };
 
     if botname.len() > 30 {
@@ -225,12 +379,32 @@ async fn post_bot(
     if botname.len() == 0 {
         return (StatusCode::BAD_REQUEST, Json("Bot name cannot be empty.")).into_response();
     }
+    if bot.games.is_empty() {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json("Choose at least one game for the bot to play."),
+        )
+            .into_response();
+    }
+    if let Some(language) = bot.language() {
+        if WASM_RUNTIME.interpreters.get(language).is_none() {
+            return (
+                StatusCode::BAD_REQUEST,
+                Json(format!(
+                    "{} bots are not available on this server.",
+                    language
+                )),
+            )
+                .into_response();
+        }
+    }
 
     let result = tournament::add_bot(
         &shared_state.db_pool,
         &shared_state.bucket_name,
         &mut bot,
         true,
+        &shared_state.tournament_config,
     )
     .await;
     return match result {
@@ -247,27 +421,183 @@ async fn post_bot(
     };
 }
 
+async fn get_bots(
+    State(shared_state): State<Arc<SharedState>>,
+    Query(query): Query<LeaderboardQuery>,
+) -> Response {
+    match leaderboard::get_leaderboard(&shared_state.db_pool, &query).await {
+        Ok(leaderboard) => (StatusCode::OK, Json(leaderboard)).into_response(),
+        Err(e) => {
+            println!("Error: {}", e);
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json("Unexpected error occurred".to_string()),
+            )
+                .into_response();
+        }
+    }
+}
+
+async fn get_languages() -> Response {
+    return (StatusCode::OK, Json(WASM_RUNTIME.interpreters.languages())).into_response();
+}
+
+async fn list_tournaments(
+    State(shared_state): State<Arc<SharedState>>,
+    Query(query): Query<TournamentListQuery>,
+) -> Response {
+    match history::list_tournaments(&shared_state.db_pool, &query).await {
+        Ok(tournaments) => (StatusCode::OK, Json(tournaments)).into_response(),
+        Err(e) => {
+            println!("Error: {}", e);
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json("Unexpected error occurred".to_string()),
+            )
+                .into_response();
+        }
+    }
+}
+
+fn is_admin(shared_state: &SharedState, headers: &HeaderMap) -> bool {
+    let admin_token = match &shared_state.admin_token {
+        Some(admin_token) => admin_token,
+        None => return false,
+    };
+    let authorization = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok());
+    return authorization == Some(&format!("Bearer {}", admin_token));
+}
+
+/// Queues a tournament with its own settings: the server's config, with any of its fields
+/// replaced by the ones in the request. Queued tournaments are played before the server goes
+/// back to its own.
+async fn queue_tournament(
+    State(shared_state): State<Arc<SharedState>>,
+    headers: HeaderMap,
+    Json(overrides): Json<serde_json::Value>,
+) -> Response {
+    if !is_admin(&shared_state, &headers) {
+        return (StatusCode::UNAUTHORIZED, Json("Not allowed.")).into_response();
+    }
+    let config = match shared_state.tournament_config.with_overrides(overrides) {
+        Ok(config) => config,
+        Err(e) => {
+            return (
+                StatusCode::BAD_REQUEST,
+                Json(format!("Invalid tournament config: {}", e)),
+            )
+                .into_response();
+        }
+    };
+    let mut queued_tournaments = shared_state.queued_tournaments.lock().unwrap();
+    queued_tournaments.push_back(config.clone());
+    println!(
+        "Queued a tournament, {} in the queue.",
+        queued_tournaments.len()
+    );
+    return (StatusCode::OK, Json(config)).into_response();
+}
+
+/// Queues a stored tournament to be played again with the same config, seed and bots, e.g.
+/// to check a disputed result.
+async fn rerun_tournament(
+    State(shared_state): State<Arc<SharedState>>,
+    headers: HeaderMap,
+    Path(tournament_id): Path<i32>,
+) -> Response {
+    if !is_admin(&shared_state, &headers) {
+        return (StatusCode::UNAUTHORIZED, Json("Not allowed.")).into_response();
+    }
+    let config = match history::get_rerun_config(&shared_state.db_pool, tournament_id).await {
+        Ok(Some(config)) => config,
+        Ok(None) => return (StatusCode::NOT_FOUND, Json("Tournament not found.")).into_response(),
+        Err(e) => {
+            println!("Error: {}", e);
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json("Unexpected error occurred".to_string()),
+            )
+                .into_response();
+        }
+    };
+    if config.seed.is_none() {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json("Tournament was played without a seed, so it can't be replayed."),
+        )
+            .into_response();
+    }
+    let mut queued_tournaments = shared_state.queued_tournaments.lock().unwrap();
+    queued_tournaments.push_back(config.clone());
+    println!(
+        "Queued a rerun of tournament {}, {} in the queue.",
+        tournament_id,
+        queued_tournaments.len()
+    );
+    return (StatusCode::OK, Json(config)).into_response();
+}
+
+async fn get_tournament(
+    State(shared_state): State<Arc<SharedState>>,
+    Path(tournament_id): Path<i32>,
+) -> Response {
+    match history::get_tournament(&shared_state.db_pool, tournament_id).await {
+        Ok(Some(tournament)) => (StatusCode::OK, Json(tournament)).into_response(),
+        Ok(None) => (StatusCode::NOT_FOUND, Json("Tournament not found.")).into_response(),
+        Err(e) => {
+            println!("Error: {}", e);
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json("Unexpected error occurred".to_string()),
+            )
+                .into_response();
+        }
+    }
+}
+
 #[derive(Deserialize)]
 struct TestBotRequest {
     botcode: String,
     stdin: Option<String>,
     run_type: BotRunType,
+    #[serde(default)]
+    session: bool,
+    /// The interpreter for Script bots.
+    #[serde(default)]
+    language: String,
+    /// The game to test the bot in, if not the server's.
+    game: Option<GameKind>,
 }
 
-async fn test_bot(Json(payload): Json<TestBotRequest>) -> Response {
+async fn test_bot(
+    State(shared_state): State<Arc<SharedState>>,
+    Json(payload): Json<TestBotRequest>,
+) -> Response {
     let botcode = payload.botcode;
 
     let bot: BotDetails = BotDetails {
         id: None,
-        run_type: payload.run_type,
+        run_type: payload.run_type.for_language(&payload.language),
         name: "test".to_string(),
         code: botcode.clone(),
         wasm_path: "".to_string(),
         wasm_bytes: None,
+        wasm_kind: WasmKind::CoreModule,
+        session: payload.session,
+        language: payload.language,
+        games: vec![],
     };
 
-    let result = tournament::test_bot(&bot, payload.stdin).await;
-    return (StatusCode::OK, Json(result)).into_response();
+    let config = &TournamentConfig {
+        game: payload.game.unwrap_or(shared_state.tournament_config.game),
+        ..shared_state.tournament_config.clone()
+    };
+    return with_game!(config, game => {
+        let result = tournament::test_bot(&game, &bot, payload.stdin, &config.limits).await;
+        (StatusCode::OK, Json(result)).into_response()
+    });
 }
 
 async fn ws_handler(
@@ -340,11 +670,36 @@ async fn upload_wasm(
     mut form_data: Multipart,
 ) -> Response {
     let mut botname = "".to_string();
+    let mut session = false;
+    let mut games = vec![];
+    let mut run_type = BotRunType::Wasi;
     let mut data: Bytes = Bytes::from("".to_string());
     while let Some(field) = form_data.next_field().await.unwrap() {
         let field_name = field.name().unwrap_or_default().to_string();
         if field_name == "botname".to_string() {
             botname = field.text().await.unwrap();
+        } else if field_name == "run_type".to_string() {
+            run_type = match field.text().await.unwrap_or_default().as_str() {
+                "Component" => BotRunType::Component,
+                "Python" => BotRunType::Python,
+                "JavaScript" => BotRunType::JavaScript,
+                _ => BotRunType::Wasi,
+            };
+        } else if field_name == "session".to_string() {
+            session = field.text().await.unwrap_or_default() == "true";
+        } else if field_name == "games".to_string() {
+            // One field for each game, with the game's name as in the tournament config.
+            let game_name = field.text().await.unwrap_or_default();
+            match serde_json::from_value::<GameKind>(serde_json::Value::String(game_name.clone())) {
+                Ok(game) => games.push(game),
+                Err(_) => {
+                    return (
+                        StatusCode::BAD_REQUEST,
+                        Json(format!("Unknown game: {}", game_name)),
+                    )
+                        .into_response();
+                }
+            }
         } else if field_name == "wasm_file".to_string() {
             data = field.bytes().await.unwrap_or_default();
         }
@@ -363,26 +718,88 @@ async fn upload_wasm(
 
     let mut bot: BotDetails = BotDetails {
         id: None,
-        run_type: BotRunType::Wasi,
+        run_type,
         name: botname.clone(),
         code: "".to_string(),
         wasm_path: "".to_string(),
         wasm_bytes: Some(data_vec),
+        wasm_kind: WasmKind::CoreModule,
+        session,
+        language: "".to_string(),
+        games: if games.is_empty() {
+            default_games()
+        } else {
+            games
+        },
     };
 
-    let bot_run_result = tournament::test_bot(&bot, None).await;
-    match bot_run_result.result {
-        SPROption::Invalid => {
-            let reason = bot_run_result
-                .invalid_reason
-                .unwrap_or("Unknown reason".to_string());
+    // Script bots are uploaded as a zip archive of their source files.
+    if let Some(language) = bot.language() {
+        let interpreter = match WASM_RUNTIME.interpreters.get(language) {
+            Some(interpreter) => interpreter,
+            None => {
+                return (
+                    StatusCode::BAD_REQUEST,
+                    Json(format!(
+                        "{} bots are not available on this server.",
+                        language
+                    )),
+                )
+                    .into_response();
+            }
+        };
+        if let Err(message) = bot_archive::check_archive(&data, &interpreter.config.source_filename)
+        {
+            return (StatusCode::BAD_REQUEST, Json(message)).into_response();
+        }
+    } else {
+        bot.wasm_kind = match WasmKind::detect(&data) {
+            Some(wasm_kind) => wasm_kind,
+            None => {
+                return (
+                    StatusCode::BAD_REQUEST,
+                    Json("File is not a WebAssembly module or component.".to_string()),
+                )
+                    .into_response();
+            }
+        };
+        if let (BotRunType::Component, WasmKind::CoreModule) = (&bot.run_type, bot.wasm_kind) {
+            return (
+                StatusCode::BAD_REQUEST,
+                Json(
+                    "snippy:bot/player bots must be components, e.g. built for wasm32-wasip2."
+                        .to_string(),
+                ),
+            )
+                .into_response();
+        }
+    }
+
+    // Bots are tested in every game they say they can play, so e.g. a snippy:bot/player
+    // component can't enter prisoner's dilemma tournaments.
+    for game_kind in &bot.games {
+        let config = &TournamentConfig {
+            game: *game_kind,
+            ..shared_state.tournament_config.clone()
+        };
+        let (passed, invalid_reason) = with_game!(config, game => {
+            let bot_run_result = tournament::test_bot(&game, &bot, None, &config.limits).await;
+            (
+                bot_run_result.result != game.invalid_move(),
+                bot_run_result.invalid_reason,
+            )
+        });
+        if !passed {
+            let reason = invalid_reason.unwrap_or("Unknown reason".to_string());
             return (
                 StatusCode::BAD_REQUEST,
-                Json(format!("Bot did not pass a test run. {}", reason)),
+                Json(format!(
+                    "Bot did not pass a test run of {:?}. {}",
+                    game_kind, reason
+                )),
             )
                 .into_response();
         }
-        _ => (),
     }
 
     match tournament::add_bot(
@@ -390,6 +807,7 @@ async fn upload_wasm(
         &shared_state.bucket_name,
         &mut bot,
         false,
+        &shared_state.tournament_config,
     )
     .await
     {
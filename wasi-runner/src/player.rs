@@ -0,0 +1,336 @@
+use anyhow::Result;
+use std::time::Instant;
+use tokio::time::timeout;
+use wasmtime::component::InstancePre;
+use wasmtime_wasi::pipe::MemoryOutputPipe;
+use wasmtime_wasi::WasiCtxBuilder;
+
+use crate::game::{Game, GameKind};
+use crate::limits::{BotLimits, LimitReached};
+use crate::seed;
+use crate::tournament::{
+    self, BotDetails, BotRunInput, BotRunResult, ComponentRunStates, DebugEntry, RoundResult,
+    TIMEOUT_GRACE,
+};
+
+mod bindings {
+    wasmtime::component::bindgen!({
+        path: "wit/player.wit",
+        world: "player",
+        async: true,
+    });
+}
+
+mod game_bindings {
+    wasmtime::component::bindgen!({
+        path: "wit/player.wit",
+        world: "game-player",
+        async: true,
+    });
+}
+
+use bindings::PlayerPre;
+use game_bindings::GamePlayerPre;
+
+/// The moves in the `player` world, with the names the game reads them from. Bots built for
+/// it can only play rock-paper-scissors.
+const PLAYER_MOVES: [(bindings::Move, &str); 3] = [
+    (bindings::Move::Rock, "rock"),
+    (bindings::Move::Paper, "paper"),
+    (bindings::Move::Scissors, "scissors"),
+];
+
+/// The moves in the `game-player` world. Each game reads the ones that are its moves, and the
+/// rest are invalid.
+const GAME_PLAYER_MOVES: [(game_bindings::Move, &str); 7] = [
+    (game_bindings::Move::Rock, "rock"),
+    (game_bindings::Move::Paper, "paper"),
+    (game_bindings::Move::Scissors, "scissors"),
+    (game_bindings::Move::Lizard, "lizard"),
+    (game_bindings::Move::Spock, "spock"),
+    (game_bindings::Move::Cooperate, "cooperate"),
+    (game_bindings::Move::Defect, "defect"),
+];
+
+fn to_wit_move<G: Game, M: Copy>(
+    game: &G,
+    wit_moves: &[(M, &str)],
+    play: &G::Move,
+) -> Result<M, String> {
+    if *play == game.invalid_move() {
+        return Err("Invalid plays can't be sent to component bots.".to_string());
+    }
+    wit_moves
+        .iter()
+        .find(|(_, name)| game.parse_move(name) == *play)
+        .map(|(wit_move, _)| *wit_move)
+        .ok_or_else(|| format!("{:?} can't be sent to component bots.", play))
+}
+
+fn to_wit_moves<G: Game, M: Copy>(
+    game: &G,
+    wit_moves: &[(M, &str)],
+    plays: &Vec<G::Move>,
+) -> Result<Vec<M>, String> {
+    plays
+        .iter()
+        .map(|play| to_wit_move(game, wit_moves, play))
+        .collect()
+}
+
+fn from_wit_move<G: Game, M: PartialEq>(game: &G, wit_moves: &[(M, &str)], play: M) -> G::Move {
+    match wit_moves.iter().find(|(wit_move, _)| *wit_move == play) {
+        Some((_, name)) => game.parse_move(name),
+        None => game.invalid_move(),
+    }
+}
+
+fn to_player_state<G: Game>(
+    game: &G,
+    input: &BotRunInput<G::Move>,
+) -> Result<bindings::GameState, String> {
+    Ok(bindings::GameState {
+        version: input.version,
+        botname: input.botname.clone(),
+        opponent: input.opponent.clone(),
+        round: input.round,
+        history: to_wit_moves(game, &PLAYER_MOVES, &input.history)?,
+        opponent_history: to_wit_moves(game, &PLAYER_MOVES, &input.opponent_history)?,
+        results: input
+            .results
+            .iter()
+            .map(|result| match result {
+                RoundResult::Win => bindings::RoundResult::Win,
+                RoundResult::Loss => bindings::RoundResult::Loss,
+                RoundResult::Draw => bindings::RoundResult::Draw,
+            })
+            .collect(),
+        score: bindings::Score {
+            wins: input.score.wins,
+            losses: input.score.losses,
+            draws: input.score.draws,
+        },
+        match_id: input.match_id.clone(),
+        tournament_id: input.tournament_id,
+    })
+}
+
+fn to_game_player_state<G: Game>(
+    game: &G,
+    input: &BotRunInput<G::Move>,
+) -> Result<game_bindings::GameState, String> {
+    Ok(game_bindings::GameState {
+        version: input.version,
+        game: match game.kind() {
+            GameKind::RockPaperScissors => game_bindings::Game::RockPaperScissors,
+            GameKind::RockPaperScissorsLizardSpock => {
+                game_bindings::Game::RockPaperScissorsLizardSpock
+            }
+            GameKind::PrisonersDilemma => game_bindings::Game::PrisonersDilemma,
+        },
+        botname: input.botname.clone(),
+        opponent: input.opponent.clone(),
+        round: input.round,
+        history: to_wit_moves(game, &GAME_PLAYER_MOVES, &input.history)?,
+        opponent_history: to_wit_moves(game, &GAME_PLAYER_MOVES, &input.opponent_history)?,
+        results: input
+            .results
+            .iter()
+            .map(|result| match result {
+                RoundResult::Win => game_bindings::RoundResult::Win,
+                RoundResult::Loss => game_bindings::RoundResult::Loss,
+                RoundResult::Draw => game_bindings::RoundResult::Draw,
+            })
+            .collect(),
+        score: game_bindings::Score {
+            wins: input.score.wins,
+            losses: input.score.losses,
+            draws: input.score.draws,
+        },
+        match_id: input.match_id.clone(),
+        tournament_id: input.tournament_id,
+    })
+}
+
+/// A component bot ready to be instantiated, with its input for the world it exports.
+enum ComponentPlayer {
+    GamePlayer(GamePlayerPre<ComponentRunStates>, game_bindings::GameState),
+    Player(PlayerPre<ComponentRunStates>, bindings::GameState),
+}
+
+/// Checks which world a component exports, and that the world can play the game.
+fn component_player<G: Game>(
+    game: &G,
+    component: &InstancePre<ComponentRunStates>,
+    input: &BotRunInput<G::Move>,
+) -> Result<ComponentPlayer, String> {
+    if let Ok(game_player_pre) = GamePlayerPre::new(component.clone()) {
+        let state = to_game_player_state(game, input)?;
+        return Ok(ComponentPlayer::GamePlayer(game_player_pre, state));
+    }
+    let player_pre = match PlayerPre::new(component.clone()) {
+        Ok(player_pre) => player_pre,
+        Err(e) => {
+            return Err(format!(
+                "Component does not export the snippy:bot/game-player or snippy:bot/player world. Error: {}",
+                e
+            ))
+        }
+    };
+    if game.kind() != GameKind::RockPaperScissors {
+        return Err(
+            "snippy:bot/player components can only play rock-paper-scissors. Build the bot for the snippy:bot/game-player world to play other games."
+                .to_string(),
+        );
+    }
+    let state = to_player_state(game, input)?;
+    return Ok(ComponentPlayer::Player(player_pre, state));
+}
+
+/// Runs a bot that's a component exporting the `snippy:bot/game-player` or `snippy:bot/player`
+/// world. It's given the same input as command bots, but as WIT types instead of JSON on stdin.
+pub async fn run_player_bot<G: Game>(
+    game: &G,
+    bot_details: &BotDetails,
+    input: String,
+    limits: &BotLimits,
+    seed: u64,
+) -> BotRunResult<G::Move> {
+    let invalid = |message: String| BotRunResult {
+        stdin: input.clone(),
+        stdout: "".to_string(),
+        stderr: "".to_string(),
+        duration: 0.0,
+        result: game.invalid_move(),
+        invalid_reason: Some(message),
+        debug: vec![],
+        annotation: None,
+        usage: None,
+    };
+
+    let bot_input = match serde_json::from_str::<BotRunInput<G::Move>>(&input) {
+        Ok(bot_input) => bot_input,
+        Err(e) => return invalid(format!("Could not read bot input. Error: {}", e)),
+    };
+    let loaded = match tournament::load_bot_component(bot_details) {
+        Ok(loaded) => loaded,
+        Err(message) => return invalid(message),
+    };
+    let player = match component_player(game, &loaded.component, &bot_input) {
+        Ok(player) => player,
+        Err(message) => return invalid(message),
+    };
+
+    // Player bots don't need stdin or stdout, but can still print to them for debugging.
+    let stdout = MemoryOutputPipe::new(limits.output_bytes());
+    let stderr = MemoryOutputPipe::new(limits.output_bytes());
+    let mut wasi_ctx_builder = WasiCtxBuilder::new();
+    wasi_ctx_builder
+        .args(&loaded.args)
+        .stdout(stdout.clone())
+        .stderr(stderr.clone());
+    seed::add_seeded_sources(&mut wasi_ctx_builder, seed);
+    let wasi = wasi_ctx_builder.build();
+    let mut store = match tournament::new_bot_store(wasi, limits) {
+        Ok(store) => store,
+        Err(e) => return invalid(format!("Could not set up the bot's store. Error: {}", e)),
+    };
+
+    let start = Instant::now();
+    let played = match &player {
+        ComponentPlayer::GamePlayer(game_player_pre, state) => {
+            match game_player_pre.instantiate_async(&mut store).await {
+                Ok(bot) => {
+                    let instantiation_time = start.elapsed();
+                    let result = timeout(
+                        limits.time() + TIMEOUT_GRACE,
+                        bot.call_play(&mut store, state),
+                    )
+                    .await
+                    .map(|result| {
+                        result.map(|turn| {
+                            let debug = turn
+                                .debug
+                                .into_iter()
+                                .map(|entry| DebugEntry {
+                                    key: entry.key,
+                                    value: entry.value,
+                                })
+                                .collect();
+                            (from_wit_move(game, &GAME_PLAYER_MOVES, turn.move_), debug)
+                        })
+                    });
+                    Ok((instantiation_time, result))
+                }
+                Err(e) => Err(e),
+            }
+        }
+        ComponentPlayer::Player(player_pre, state) => {
+            match player_pre.instantiate_async(&mut store).await {
+                Ok(bot) => {
+                    let instantiation_time = start.elapsed();
+                    let result = timeout(
+                        limits.time() + TIMEOUT_GRACE,
+                        bot.call_play(&mut store, state),
+                    )
+                    .await
+                    .map(|result| {
+                        result.map(|turn| {
+                            let debug = turn
+                                .debug
+                                .into_iter()
+                                .map(|entry| DebugEntry {
+                                    key: entry.key,
+                                    value: entry.value,
+                                })
+                                .collect();
+                            (from_wit_move(game, &PLAYER_MOVES, turn.move_), debug)
+                        })
+                    });
+                    Ok((instantiation_time, result))
+                }
+                Err(e) => Err(e),
+            }
+        }
+    };
+    let (instantiation_time, result) = match played {
+        Ok(played) => played,
+        Err(e) => {
+            return invalid(format!(
+                "Could not instantiate Wasm component. Error: {}",
+                e
+            ))
+        }
+    };
+    let duration = start.elapsed();
+    println!("Wasm stopped after {}s", duration.as_secs_f32());
+
+    let mut run_result = invalid("".to_string());
+    run_result.stdout = String::from_utf8_lossy(&stdout.contents()).to_string();
+    run_result.stderr = String::from_utf8_lossy(&stderr.contents()).to_string();
+    run_result.duration = duration.as_secs_f32();
+    run_result.usage = Some(tournament::resource_usage(
+        &store,
+        limits,
+        instantiation_time,
+    ));
+    match result {
+        Ok(Ok((play, debug))) => {
+            run_result.result = play;
+            run_result.invalid_reason = None;
+            run_result.debug = debug;
+        }
+        Ok(Err(e)) => {
+            if let Some(limit) = LimitReached::from_error(&e) {
+                run_result.invalid_reason = Some(limit.message(limits));
+            } else {
+                println!("Runtime error: {}", e);
+                run_result.invalid_reason = Some("Program failed while playing.".to_string());
+            }
+        }
+        Err(_) => {
+            run_result.invalid_reason = Some(LimitReached::Time.message(limits));
+        }
+    }
+    return run_result;
+}
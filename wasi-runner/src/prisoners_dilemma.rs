@@ -0,0 +1,175 @@
+use anyhow::{anyhow, Result};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+
+use crate::game::{Game, GameKind};
+use crate::seed;
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub enum PDOption {
+    Cooperate = 0,
+    Defect,
+    Invalid,
+}
+
+impl PDOption {
+    pub fn to_db_value(&self) -> i32 {
+        self.clone() as i32
+    }
+
+    pub fn from_db_value(value: i32) -> PDOption {
+        match value {
+            0 => PDOption::Cooperate,
+            1 => PDOption::Defect,
+            _ => PDOption::Invalid,
+        }
+    }
+}
+
+/// Points for each outcome of a round. For it to be a dilemma, temptation > reward >
+/// punishment > sucker.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Payoffs {
+    /// For defecting against a bot that cooperated.
+    pub temptation: u32,
+    /// For both cooperating.
+    pub reward: u32,
+    /// For both defecting.
+    pub punishment: u32,
+    /// For cooperating with a bot that defected.
+    pub sucker: u32,
+}
+
+impl Default for Payoffs {
+    fn default() -> Payoffs {
+        Payoffs {
+            temptation: 5,
+            reward: 3,
+            punishment: 1,
+            sucker: 0,
+        }
+    }
+}
+
+/// Reads payoffs written as `temptation,reward,punishment,sucker`, e.g. `5,3,1,0`.
+impl FromStr for Payoffs {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Payoffs> {
+        let values = s
+            .split(',')
+            .map(|value| value.trim().parse::<u32>())
+            .collect::<std::result::Result<Vec<u32>, _>>()?;
+        match values[..] {
+            [temptation, reward, punishment, sucker] => Ok(Payoffs {
+                temptation,
+                reward,
+                punishment,
+                sucker,
+            }),
+            _ => Err(anyhow!("Expected 4 payoffs, got {}", values.len())),
+        }
+    }
+}
+
+/// The iterated prisoner's dilemma. Each round both bots either cooperate or defect, and
+/// the winner of a match is the bot with the highest total payoff over all its rounds.
+///
+/// The number of rounds is picked at random for each match, between `min_rounds` and
+/// `max_rounds`, so bots can't know which round is the last and defect in it. Set them to
+/// the same number for matches of a fixed length.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(default)]
+pub struct PrisonersDilemma {
+    pub payoffs: Payoffs,
+    pub min_rounds: u32,
+    pub max_rounds: u32,
+}
+
+impl Default for PrisonersDilemma {
+    fn default() -> PrisonersDilemma {
+        PrisonersDilemma {
+            payoffs: Payoffs::default(),
+            min_rounds: 150,
+            max_rounds: 250,
+        }
+    }
+}
+
+impl PrisonersDilemma {
+    /// Sets the number of rounds from either a single number, e.g. `200`, or a range, e.g.
+    /// `150-250`.
+    pub fn set_rounds(&mut self, rounds: &str) -> Result<()> {
+        let (min_rounds, max_rounds) = match rounds.split_once('-') {
+            Some((min_rounds, max_rounds)) => (min_rounds.parse()?, max_rounds.parse()?),
+            None => (rounds.parse()?, rounds.parse()?),
+        };
+        if min_rounds == 0 || min_rounds > max_rounds {
+            return Err(anyhow!("Invalid number of rounds: {}", rounds));
+        }
+        self.min_rounds = min_rounds;
+        self.max_rounds = max_rounds;
+        return Ok(());
+    }
+}
+
+impl Game for PrisonersDilemma {
+    type Move = PDOption;
+
+    fn kind(&self) -> GameKind {
+        GameKind::PrisonersDilemma
+    }
+
+    fn moves(&self) -> Vec<PDOption> {
+        vec![PDOption::Cooperate, PDOption::Defect]
+    }
+
+    fn invalid_move(&self) -> PDOption {
+        PDOption::Invalid
+    }
+
+    fn parse_move(&self, line: &str) -> PDOption {
+        match line.to_lowercase().as_str() {
+            "cooperate" => PDOption::Cooperate,
+            "defect" => PDOption::Defect,
+            _ => PDOption::Invalid,
+        }
+    }
+
+    fn move_to_db_value(&self, play: &PDOption) -> i32 {
+        play.to_db_value()
+    }
+
+    fn move_from_db_value(&self, value: i32) -> PDOption {
+        PDOption::from_db_value(value)
+    }
+
+    fn payoff(&self, play: &PDOption, opponent_play: &PDOption) -> (u32, u32) {
+        let payoffs = &self.payoffs;
+        match (play, opponent_play) {
+            (PDOption::Cooperate, PDOption::Cooperate) => (payoffs.reward, payoffs.reward),
+            (PDOption::Defect, PDOption::Cooperate) => (payoffs.temptation, payoffs.sucker),
+            (PDOption::Cooperate, PDOption::Defect) => (payoffs.sucker, payoffs.temptation),
+            (PDOption::Defect, PDOption::Defect) => (payoffs.punishment, payoffs.punishment),
+            _ => (0, 0),
+        }
+    }
+
+    fn rounds(&self, seed: u64) -> u32 {
+        let max_rounds = self.max_rounds.max(self.min_rounds);
+        seed::seeded_rng(seed).gen_range(self.min_rounds..=max_rounds)
+    }
+
+    fn round_range(&self) -> (u32, u32) {
+        (self.min_rounds, self.max_rounds.max(self.min_rounds))
+    }
+
+    fn win_threshold(&self, _rounds: u32) -> Option<u32> {
+        None
+    }
+
+    fn ranks_by_payoff(&self) -> bool {
+        true
+    }
+}
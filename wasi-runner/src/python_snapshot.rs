@@ -0,0 +1,24 @@
+use anyhow::Result;
+use std::time::Instant;
+use wizer::Wizer;
+
+/// Export that Wizer-enabled CPython builds use to boot the interpreter and import the
+/// standard modules, so that `_start` only has to run the bot's script.
+const PYTHON_INIT_FUNC: &str = "wizer.initialize";
+
+/// Runs the interpreter's initialization once and returns a module with the resulting
+/// memory baked in, so Python bots start from an already-initialized interpreter.
+pub fn snapshot_python_interpreter(python_wasm_bytes: &[u8]) -> Result<Vec<u8>> {
+    println!("Snapshotting Python interpreter...");
+    let start = Instant::now();
+    let snapshot = Wizer::new()
+        .init_func(PYTHON_INIT_FUNC)
+        .allow_wasi(true)?
+        .wasm_bulk_memory(true)
+        .run(python_wasm_bytes)?;
+    println!(
+        "Snapshotted Python interpreter in {}s",
+        start.elapsed().as_secs_f32()
+    );
+    return Ok(snapshot);
+}
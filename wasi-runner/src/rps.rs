@@ -0,0 +1,107 @@
+use serde::{Deserialize, Serialize};
+
+use crate::game::{Game, GameKind};
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub enum SPROption {
+    Scissors = 0,
+    Paper,
+    Rock,
+    Invalid,
+}
+
+impl SPROption {
+    pub fn beats(&self, other: &SPROption) -> bool {
+        match self {
+            SPROption::Scissors => match other {
+                SPROption::Paper => true,
+                SPROption::Invalid => true,
+                _ => false,
+            },
+            SPROption::Paper => match other {
+                SPROption::Rock => true,
+                SPROption::Invalid => true,
+                _ => false,
+            },
+            SPROption::Rock => match other {
+                SPROption::Scissors => true,
+                SPROption::Invalid => true,
+                _ => false,
+            },
+            _ => false,
+        }
+    }
+
+    pub fn to_db_value(&self) -> i32 {
+        self.clone() as i32
+    }
+
+    pub fn from_db_value(value: i32) -> SPROption {
+        match value {
+            0 => SPROption::Scissors,
+            1 => SPROption::Paper,
+            2 => SPROption::Rock,
+            _ => SPROption::Invalid,
+        }
+    }
+}
+
+/// Best of 5 rounds of rock-paper-scissors. The first bot to win a majority of the rounds
+/// wins the match.
+#[derive(Clone, Default)]
+pub struct RockPaperScissors;
+
+impl Game for RockPaperScissors {
+    type Move = SPROption;
+
+    fn kind(&self) -> GameKind {
+        GameKind::RockPaperScissors
+    }
+
+    fn moves(&self) -> Vec<SPROption> {
+        vec![SPROption::Rock, SPROption::Paper, SPROption::Scissors]
+    }
+
+    fn invalid_move(&self) -> SPROption {
+        SPROption::Invalid
+    }
+
+    fn parse_move(&self, line: &str) -> SPROption {
+        match line.to_lowercase().as_str() {
+            "scissors" => SPROption::Scissors,
+            "paper" => SPROption::Paper,
+            "rock" => SPROption::Rock,
+            _ => SPROption::Invalid,
+        }
+    }
+
+    fn move_to_db_value(&self, play: &SPROption) -> i32 {
+        play.to_db_value()
+    }
+
+    fn move_from_db_value(&self, value: i32) -> SPROption {
+        SPROption::from_db_value(value)
+    }
+
+    fn payoff(&self, play: &SPROption, opponent_play: &SPROption) -> (u32, u32) {
+        if play.beats(opponent_play) {
+            (1, 0)
+        } else if opponent_play.beats(play) {
+            (0, 1)
+        } else {
+            (0, 0)
+        }
+    }
+
+    fn rounds(&self, _seed: u64) -> u32 {
+        5
+    }
+
+    fn round_range(&self) -> (u32, u32) {
+        (5, 5)
+    }
+
+    fn win_threshold(&self, rounds: u32) -> Option<u32> {
+        Some(rounds / 2 + 1)
+    }
+}
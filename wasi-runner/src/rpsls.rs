@@ -0,0 +1,114 @@
+use serde::{Deserialize, Serialize};
+
+use crate::game::{Game, GameKind};
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub enum RPSLSOption {
+    Scissors = 0,
+    Paper,
+    Rock,
+    Lizard,
+    Spock,
+    Invalid,
+}
+
+impl RPSLSOption {
+    /// Scissors cuts paper, paper covers rock, rock crushes lizard, lizard poisons Spock,
+    /// Spock smashes scissors, scissors decapitates lizard, lizard eats paper, paper
+    /// disproves Spock, Spock vaporizes rock, and rock crushes scissors.
+    pub fn beats(&self, other: &RPSLSOption) -> bool {
+        match (self, other) {
+            (RPSLSOption::Invalid, _) => false,
+            (_, RPSLSOption::Invalid) => true,
+            (RPSLSOption::Scissors, RPSLSOption::Paper | RPSLSOption::Lizard) => true,
+            (RPSLSOption::Paper, RPSLSOption::Rock | RPSLSOption::Spock) => true,
+            (RPSLSOption::Rock, RPSLSOption::Lizard | RPSLSOption::Scissors) => true,
+            (RPSLSOption::Lizard, RPSLSOption::Spock | RPSLSOption::Paper) => true,
+            (RPSLSOption::Spock, RPSLSOption::Scissors | RPSLSOption::Rock) => true,
+            _ => false,
+        }
+    }
+
+    pub fn to_db_value(&self) -> i32 {
+        self.clone() as i32
+    }
+
+    pub fn from_db_value(value: i32) -> RPSLSOption {
+        match value {
+            0 => RPSLSOption::Scissors,
+            1 => RPSLSOption::Paper,
+            2 => RPSLSOption::Rock,
+            3 => RPSLSOption::Lizard,
+            4 => RPSLSOption::Spock,
+            _ => RPSLSOption::Invalid,
+        }
+    }
+}
+
+/// Rock-paper-scissors-lizard-Spock, with the same best of 5 rounds as rock-paper-scissors.
+/// Every move beats two others and loses to the other two.
+#[derive(Clone, Default)]
+pub struct RockPaperScissorsLizardSpock;
+
+impl Game for RockPaperScissorsLizardSpock {
+    type Move = RPSLSOption;
+
+    fn kind(&self) -> GameKind {
+        GameKind::RockPaperScissorsLizardSpock
+    }
+
+    fn moves(&self) -> Vec<RPSLSOption> {
+        vec![
+            RPSLSOption::Rock,
+            RPSLSOption::Paper,
+            RPSLSOption::Scissors,
+            RPSLSOption::Lizard,
+            RPSLSOption::Spock,
+        ]
+    }
+
+    fn invalid_move(&self) -> RPSLSOption {
+        RPSLSOption::Invalid
+    }
+
+    fn parse_move(&self, line: &str) -> RPSLSOption {
+        match line.to_lowercase().as_str() {
+            "scissors" => RPSLSOption::Scissors,
+            "paper" => RPSLSOption::Paper,
+            "rock" => RPSLSOption::Rock,
+            "lizard" => RPSLSOption::Lizard,
+            "spock" => RPSLSOption::Spock,
+            _ => RPSLSOption::Invalid,
+        }
+    }
+
+    fn move_to_db_value(&self, play: &RPSLSOption) -> i32 {
+        play.to_db_value()
+    }
+
+    fn move_from_db_value(&self, value: i32) -> RPSLSOption {
+        RPSLSOption::from_db_value(value)
+    }
+
+    fn payoff(&self, play: &RPSLSOption, opponent_play: &RPSLSOption) -> (u32, u32) {
+        if play.beats(opponent_play) {
+            (1, 0)
+        } else if opponent_play.beats(play) {
+            (0, 1)
+        } else {
+            (0, 0)
+        }
+    }
+
+    fn rounds(&self, _seed: u64) -> u32 {
+        5
+    }
+
+    fn round_range(&self) -> (u32, u32) {
+        (5, 5)
+    }
+
+    fn win_threshold(&self, rounds: u32) -> Option<u32> {
+        Some(rounds / 2 + 1)
+    }
+}
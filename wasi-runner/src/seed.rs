@@ -0,0 +1,81 @@
+use rand::{RngCore, SeedableRng};
+use rand_chacha::ChaCha20Rng;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+use wasmtime_wasi::{HostMonotonicClock, HostWallClock, WasiCtxBuilder};
+
+/// Bots' wall clocks start somewhere in the year after this, depending on their seed.
+const WALL_CLOCK_BASE: Duration = Duration::from_secs(1_704_067_200); // 2024-01-01T00:00:00Z
+const WALL_CLOCK_SPREAD_SECS: u64 = 365 * 24 * 60 * 60;
+/// How far a bot's clocks move each time it reads them.
+const CLOCK_STEP: Duration = Duration::from_micros(1);
+
+/// Derives the seed for one part of a tournament (a match, a bot's run, the coin toss...)
+/// from the seed of the whole, so each part gets its own stream of numbers no matter what
+/// order they're played in.
+pub fn derive_seed(seed: u64, label: &str) -> u64 {
+    let digest = sha256::digest(format!("{}/{}", seed, label));
+    return u64::from_str_radix(&digest[..16], 16).unwrap();
+}
+
+/// A generator that gives the same numbers for the same seed, on every platform and build.
+pub fn seeded_rng(seed: u64) -> ChaCha20Rng {
+    ChaCha20Rng::seed_from_u64(seed)
+}
+
+/// Replaces the host's randomness and clocks with ones driven by the seed, so a bot sees
+/// exactly the same random numbers and times whenever it's run with that seed.
+pub fn add_seeded_sources(wasi_ctx_builder: &mut WasiCtxBuilder, seed: u64) {
+    let mut insecure_seed_rng = seeded_rng(derive_seed(seed, "insecure-seed"));
+    let insecure_seed =
+        (insecure_seed_rng.next_u64() as u128) << 64 | insecure_seed_rng.next_u64() as u128;
+    let wall_clock_start = WALL_CLOCK_BASE
+        + Duration::from_secs(derive_seed(seed, "wall-clock") % WALL_CLOCK_SPREAD_SECS);
+    wasi_ctx_builder
+        .secure_random(seeded_rng(derive_seed(seed, "secure")))
+        .insecure_random(seeded_rng(derive_seed(seed, "insecure")))
+        .insecure_random_seed(insecure_seed)
+        .wall_clock(SteppingClock::new(wall_clock_start))
+        .monotonic_clock(SteppingClock::new(Duration::ZERO));
+}
+
+/// A clock that only moves when it's read, by a fixed step each time. Real time depends on
+/// how busy the host is, so it can't be replayed.
+struct SteppingClock {
+    start: Duration,
+    reads: AtomicU64,
+}
+
+impl SteppingClock {
+    fn new(start: Duration) -> SteppingClock {
+        SteppingClock {
+            start,
+            reads: AtomicU64::new(0),
+        }
+    }
+
+    fn read(&self) -> Duration {
+        let reads = self.reads.fetch_add(1, Ordering::Relaxed);
+        return self.start + Duration::from_nanos(CLOCK_STEP.as_nanos() as u64 * reads);
+    }
+}
+
+impl HostWallClock for SteppingClock {
+    fn resolution(&self) -> Duration {
+        CLOCK_STEP
+    }
+
+    fn now(&self) -> Duration {
+        self.read()
+    }
+}
+
+impl HostMonotonicClock for SteppingClock {
+    fn resolution(&self) -> u64 {
+        CLOCK_STEP.as_nanos() as u64
+    }
+
+    fn now(&self) -> u64 {
+        self.read().as_nanos() as u64
+    }
+}
@@ -0,0 +1,340 @@
+use anyhow::anyhow;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader, DuplexStream, Lines};
+use tokio::task::JoinHandle;
+use tokio::time::timeout;
+use wasmtime::component::InstancePre;
+use wasmtime::{CallHook, Trap, UpdateDeadline};
+use wasmtime_wasi::bindings::CommandPre;
+use wasmtime_wasi::pipe::{AsyncReadStream, AsyncWriteStream, MemoryOutputPipe};
+use wasmtime_wasi::{AsyncStdinStream, AsyncStdoutStream, WasiCtx, WasiCtxBuilder};
+
+use crate::game::Game;
+use crate::limits::{BotLimits, LimitReached, ResourceUsage};
+use crate::seed;
+use crate::tournament::{
+    self, BotDetails, BotRunDir, BotRunResult, ComponentRunStates, WASM_RUNTIME,
+};
+
+/// How long a session bot that's being stopped gets to report why it stopped.
+const SESSION_STOP_GRACE: Duration = Duration::from_millis(100);
+
+/// Shared between the host and the store's callbacks. The epoch callback is where the
+/// per-round budgets are enforced while the bot is running, and the call hook records what
+/// the bot has used each time it calls the host, e.g. to print its play.
+struct RoundClock {
+    round: u32,
+    started: Instant,
+    fuel_left: u64,
+    peak_memory: usize,
+    instantiation_time: Duration,
+}
+
+/// A bot instance that stays alive for a whole match. Each round it's sent the usual JSON
+/// input as one line on stdin and answers with its play on a line of stdout. The bot's time
+/// and fuel limits apply to each round, and the first round's fuel also covers its startup.
+/// Each round's time starts when its input is sent, so time the bot spends waiting for it
+/// doesn't count.
+pub struct BotSession {
+    stdin: DuplexStream,
+    stdout: Lines<BufReader<DuplexStream>>,
+    stderr: MemoryOutputPipe,
+    stderr_offset: usize,
+    clock: Arc<Mutex<RoundClock>>,
+    limits: BotLimits,
+    rounds_played: u32,
+    task: Option<JoinHandle<String>>,
+    /// Why the session is over, once the bot has stopped or been stopped.
+    ended: Option<String>,
+}
+
+impl BotSession {
+    pub async fn start(bot_details: &BotDetails, limits: &BotLimits, seed: u64) -> BotSession {
+        let output_limit = limits.output_bytes();
+        let (stdin, guest_stdin) = tokio::io::duplex(output_limit);
+        let (guest_stdout, stdout) = tokio::io::duplex(output_limit);
+        let stderr = MemoryOutputPipe::new(output_limit);
+        let clock = Arc::new(Mutex::new(RoundClock {
+            round: 1,
+            started: Instant::now(),
+            fuel_left: limits.fuel,
+            peak_memory: 0,
+            instantiation_time: Duration::ZERO,
+        }));
+        let mut session = BotSession {
+            stdin,
+            stdout: BufReader::new(stdout).lines(),
+            stderr: stderr.clone(),
+            stderr_offset: 0,
+            clock: clock.clone(),
+            limits: limits.clone(),
+            rounds_played: 0,
+            task: None,
+            ended: None,
+        };
+
+        let loaded = match tournament::load_bot_component(bot_details) {
+            Ok(loaded) => loaded,
+            Err(message) => {
+                session.ended = Some(message);
+                return session;
+            }
+        };
+
+        let mut wasi_ctx_builder = WasiCtxBuilder::new();
+        wasi_ctx_builder
+            .args(&loaded.args)
+            .stdin(AsyncStdinStream::new(AsyncReadStream::new(guest_stdin)))
+            .stdout(AsyncStdoutStream::new(AsyncWriteStream::new(
+                output_limit,
+                guest_stdout,
+            )))
+            .stderr(stderr);
+        seed::add_seeded_sources(&mut wasi_ctx_builder, seed);
+        if let Err(message) = tournament::add_preopens(&mut wasi_ctx_builder, &loaded.preopens) {
+            session.ended = Some(message);
+            return session;
+        }
+
+        session.task = Some(tokio::spawn(run_session(
+            loaded.component,
+            wasi_ctx_builder.build(),
+            clock,
+            limits.clone(),
+            loaded.run_dir,
+        )));
+        return session;
+    }
+
+    pub async fn play_round<G: Game>(&mut self, game: &G, input: String) -> BotRunResult<G::Move> {
+        let invalid = game.invalid_move();
+        if let Some(reason) = &self.ended {
+            return invalid_result(
+                invalid,
+                input,
+                "".to_string(),
+                "".to_string(),
+                0.0,
+                reason.clone(),
+            );
+        }
+
+        {
+            let mut clock = self.clock.lock().unwrap();
+            clock.started = Instant::now();
+            if self.rounds_played > 0 {
+                clock.round += 1;
+            }
+        }
+        if self.rounds_played > 0 {
+            // Make sure the epoch callback runs as soon as the bot wakes up, so the new
+            // round's fuel is in place before it starts working on it.
+            WASM_RUNTIME.engine.increment_epoch();
+        }
+        self.rounds_played += 1;
+
+        let start = Instant::now();
+        let mut stdout_lines: Vec<String> = vec![];
+        let mut stdout_len = 0;
+        let mut play = invalid.clone();
+        let mut annotation = None;
+        let line = format!("{}\n", input);
+        let answer = timeout(self.limits.time(), async {
+            self.stdin.write_all(line.as_bytes()).await?;
+            self.stdin.flush().await?;
+            while let Some(line) = self.stdout.next_line().await? {
+                stdout_len += line.len() + 1;
+                (play, annotation) = tournament::parse_play(game, &line);
+                stdout_lines.push(line);
+                if play != invalid {
+                    return Ok(());
+                }
+                if stdout_len > self.limits.output_bytes() {
+                    return Err(anyhow!(
+                        "Program wrote too much output without a valid play."
+                    ));
+                }
+            }
+            return Err(anyhow!("Program exited before the match was over."));
+        })
+        .await;
+        let duration = start.elapsed().as_secs_f32();
+
+        if let Err(reason) = match answer {
+            Ok(Ok(())) => Ok(()),
+            Ok(Err(e)) => Err(e.to_string()),
+            Err(_) => Err(limit_message(LimitReached::Time, &self.limits)),
+        } {
+            self.ended = Some(self.stop(reason).await);
+        }
+
+        let stdout = stdout_lines.join("\n");
+        let stderr = self.take_stderr();
+        let usage = self.usage();
+        let mut result = match &self.ended {
+            Some(reason) => {
+                invalid_result(invalid, input, stdout, stderr, duration, reason.clone())
+            }
+            None => BotRunResult {
+                stdin: input,
+                stdout,
+                stderr,
+                duration,
+                result: play,
+                invalid_reason: None,
+                debug: vec![],
+                annotation,
+                usage: None,
+            },
+        };
+        result.usage = Some(usage);
+        return result;
+    }
+
+    /// What the bot used in the round so far. Instantiation only happens before the first
+    /// round, whose fuel also covers it.
+    fn usage(&self) -> ResourceUsage {
+        let clock = self.clock.lock().unwrap();
+        ResourceUsage {
+            fuel_consumed: self.limits.fuel.saturating_sub(clock.fuel_left),
+            peak_memory: clock.peak_memory,
+            instantiation_time: if self.rounds_played == 1 {
+                clock.instantiation_time.as_secs_f32()
+            } else {
+                0.0
+            },
+            limits: self.limits.clone(),
+        }
+    }
+
+    /// Stops the bot and returns the reason the session ended. If the bot is stopping by
+    /// itself, its own reason (e.g. running out of fuel) is more useful than what the host
+    /// noticed, so it gets a moment to finish first.
+    async fn stop(&mut self, host_reason: String) -> String {
+        let mut task = match self.task.take() {
+            Some(task) => task,
+            None => return host_reason,
+        };
+        if let Ok(Ok(reason)) = timeout(SESSION_STOP_GRACE, &mut task).await {
+            return reason;
+        }
+        task.abort();
+        return host_reason;
+    }
+
+    fn take_stderr(&mut self) -> String {
+        let contents = self.stderr.contents();
+        let stderr = String::from_utf8_lossy(&contents[self.stderr_offset..]).to_string();
+        self.stderr_offset = contents.len();
+        return stderr;
+    }
+}
+
+impl Drop for BotSession {
+    fn drop(&mut self) {
+        if let Some(task) = &self.task {
+            task.abort();
+        }
+    }
+}
+
+fn limit_message(limit: LimitReached, limits: &BotLimits) -> String {
+    match limit {
+        LimitReached::Time => format!(
+            "Timeout! Bots in session mode are limited to {}ms per round",
+            limits.time_ms
+        ),
+        LimitReached::Fuel => format!(
+            "Program ran out of fuel: It reached the limit of {} wasm instructions in one round.",
+            limits.fuel
+        ),
+    }
+}
+
+fn invalid_result<M>(
+    invalid: M,
+    input: String,
+    stdout: String,
+    stderr: String,
+    duration: f32,
+    reason: String,
+) -> BotRunResult<M> {
+    BotRunResult {
+        stdin: input,
+        stdout,
+        stderr,
+        duration,
+        result: invalid,
+        invalid_reason: Some(reason),
+        debug: vec![],
+        annotation: None,
+        usage: None,
+    }
+}
+
+/// Runs the bot until it exits or breaks a limit, and returns why it stopped.
+async fn run_session(
+    component: InstancePre<ComponentRunStates>,
+    wasi_ctx: WasiCtx,
+    clock: Arc<Mutex<RoundClock>>,
+    limits: BotLimits,
+    // Kept until the bot has stopped, since it's mounted in the bot's filesystem.
+    _run_dir: Option<BotRunDir>,
+) -> String {
+    let mut store = match tournament::new_bot_store(wasi_ctx, &limits) {
+        Ok(store) => store,
+        Err(e) => return format!("Could not set up the bot's store. Error: {}", e),
+    };
+
+    // Replaces the store's usual deadline callback. It runs on every epoch tick while the
+    // bot is running, tops up the fuel when a new round starts and stops the bot once it's
+    // over time for the round.
+    let round_fuel = limits.fuel;
+    let round_time = limits.time();
+    let mut fuel_round = 1;
+    let epoch_clock = clock.clone();
+    store.epoch_deadline_callback(move |mut store| {
+        let mut clock = epoch_clock.lock().unwrap();
+        if clock.round != fuel_round {
+            fuel_round = clock.round;
+            store.set_fuel(round_fuel)?;
+            clock.fuel_left = round_fuel;
+        } else if clock.started.elapsed() > round_time {
+            return Err(Trap::Interrupt.into());
+        }
+        return Ok(UpdateDeadline::Yield(1));
+    });
+    let usage_clock = clock.clone();
+    store.call_hook(move |store, hook| {
+        if let CallHook::CallingHost = hook {
+            let mut clock = usage_clock.lock().unwrap();
+            clock.fuel_left = store.get_fuel()?;
+            clock.peak_memory = store.data().limiter.peak_memory;
+        }
+        return Ok(());
+    });
+
+    let start = Instant::now();
+    let command = match CommandPre::new(component) {
+        Ok(command_pre) => command_pre.instantiate_async(&mut store).await,
+        Err(e) => Err(e),
+    };
+    clock.lock().unwrap().instantiation_time = start.elapsed();
+    let command = match command {
+        Ok(command) => command,
+        Err(e) => return format!("Could not instantiate Wasm component. Error: {}", e),
+    };
+
+    return match command.wasi_cli_run().call_run(&mut store).await {
+        Ok(Ok(())) => "Program exited before the match was over.".to_string(),
+        Ok(Err(())) => "Program did not exit successfully.".to_string(),
+        Err(e) => match LimitReached::from_error(&e) {
+            Some(limit) => limit_message(limit, &limits),
+            None => {
+                println!("Runtime error: {}", e);
+                "Program did not exit successfully.".to_string()
+            }
+        },
+    };
+}
@@ -0,0 +1,169 @@
+use serde::Serialize;
+use std::collections::HashMap;
+
+use crate::game::Game;
+use crate::tournament::{MatchOutcome, MatchState, RoundResult};
+
+const POINTS_FOR_WIN: u32 = 3;
+const POINTS_FOR_DRAW: u32 = 1;
+
+#[derive(Clone, Serialize, Default)]
+pub struct StandingsEntry {
+    pub rank: usize,
+    pub name: String,
+    pub played: u32,
+    pub wins: u32,
+    pub draws: u32,
+    pub losses: u32,
+    pub points: u32,
+    pub rounds_won: u32,
+    pub rounds_lost: u32,
+    /// Total payoff of every round the bot played.
+    pub payoff: u32,
+}
+
+impl StandingsEntry {
+    fn round_difference(&self) -> i64 {
+        self.rounds_won as i64 - self.rounds_lost as i64
+    }
+}
+
+/// Websocket message sent after every finished match in formats that keep a standings table.
+#[derive(Clone, Serialize)]
+pub struct StandingsUpdate {
+    pub standings: Vec<StandingsEntry>,
+}
+
+/// A finished match between two bots, reduced to what the standings need.
+struct FinishedMatch {
+    names: [String; 2],
+    points: [u32; 2],
+}
+
+fn finished_results<M>(outcomes: &[MatchOutcome<M>]) -> Vec<FinishedMatch> {
+    outcomes
+        .iter()
+        .filter(|o| o.state == MatchState::Finished && o.participants.len() == 2)
+        .map(|o| {
+            let points = if o.draw {
+                [POINTS_FOR_DRAW, POINTS_FOR_DRAW]
+            } else if o.winner == 0 {
+                [POINTS_FOR_WIN, 0]
+            } else {
+                [0, POINTS_FOR_WIN]
+            };
+            FinishedMatch {
+                names: [
+                    o.participants[0].name.clone(),
+                    o.participants[1].name.clone(),
+                ],
+                points,
+            }
+        })
+        .collect()
+}
+
+/// Builds the standings table from the match outcomes so far. A bye counts as a win.
+///
+/// Bots are ordered by points, then by points scored in matches between the tied bots
+/// (head-to-head), then by round difference, then by rounds won and finally by name. In
+/// games that rank by payoff, the total payoff comes before all of those.
+pub fn compute_standings<G: Game>(
+    game: &G,
+    bot_names: &[String],
+    outcomes: &[MatchOutcome<G::Move>],
+) -> Vec<StandingsEntry> {
+    let mut entries: HashMap<String, StandingsEntry> = bot_names
+        .iter()
+        .map(|name| {
+            (
+                name.clone(),
+                StandingsEntry {
+                    name: name.clone(),
+                    ..Default::default()
+                },
+            )
+        })
+        .collect();
+
+    for outcome in outcomes {
+        if outcome.state == MatchState::Bye && outcome.participants.len() == 1 {
+            if let Some(entry) = entries.get_mut(&outcome.participants[0].name) {
+                entry.played += 1;
+                entry.wins += 1;
+                entry.points += POINTS_FOR_WIN;
+            }
+            continue;
+        }
+        if outcome.state != MatchState::Finished || outcome.participants.len() != 2 {
+            continue;
+        }
+        for (i, participant) in outcome.participants.iter().enumerate() {
+            let opponent = &outcome.participants[1 - i];
+            let entry = entries
+                .entry(participant.name.clone())
+                .or_insert_with(|| StandingsEntry {
+                    name: participant.name.clone(),
+                    ..Default::default()
+                });
+            entry.played += 1;
+            if outcome.draw {
+                entry.draws += 1;
+                entry.points += POINTS_FOR_DRAW;
+            } else if outcome.winner == i {
+                entry.wins += 1;
+                entry.points += POINTS_FOR_WIN;
+            } else {
+                entry.losses += 1;
+            }
+            for (play, opponent_play) in participant.moves.iter().zip(opponent.moves.iter()) {
+                entry.payoff += game.payoff(play, opponent_play).0;
+                match game.round_result(play, opponent_play) {
+                    RoundResult::Win => entry.rounds_won += 1,
+                    RoundResult::Loss => entry.rounds_lost += 1,
+                    RoundResult::Draw => (),
+                }
+            }
+        }
+    }
+
+    let results = finished_results(outcomes);
+    let mut standings: Vec<StandingsEntry> = entries.into_values().collect();
+    standings.sort_by(|a, b| b.points.cmp(&a.points).then(a.name.cmp(&b.name)));
+
+    // Break ties between bots on equal points using only the matches they played against each other.
+    let mut head_to_head: HashMap<String, u32> = HashMap::new();
+    let mut start = 0;
+    while start < standings.len() {
+        let mut end = start + 1;
+        while end < standings.len() && standings[end].points == standings[start].points {
+            end += 1;
+        }
+        let tied: Vec<&String> = standings[start..end].iter().map(|e| &e.name).collect();
+        for result in &results {
+            if tied.contains(&&result.names[0]) && tied.contains(&&result.names[1]) {
+                for i in 0..2 {
+                    *head_to_head.entry(result.names[i].clone()).or_default() += result.points[i];
+                }
+            }
+        }
+        start = end;
+    }
+
+    let ranks_by_payoff = game.ranks_by_payoff();
+    standings.sort_by(|a, b| {
+        let h2h = |e: &StandingsEntry| head_to_head.get(&e.name).copied().unwrap_or(0);
+        let payoff = |e: &StandingsEntry| if ranks_by_payoff { e.payoff } else { 0 };
+        payoff(b)
+            .cmp(&payoff(a))
+            .then(b.points.cmp(&a.points))
+            .then(h2h(b).cmp(&h2h(a)))
+            .then(b.round_difference().cmp(&a.round_difference()))
+            .then(b.rounds_won.cmp(&a.rounds_won))
+            .then(a.name.cmp(&b.name))
+    });
+    for (i, entry) in standings.iter_mut().enumerate() {
+        entry.rank = i + 1;
+    }
+    standings
+}
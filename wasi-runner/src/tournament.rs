@@ -1,4 +1,4 @@
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 use aws_config::{BehaviorVersion, Region};
 use aws_sdk_s3::primitives::ByteStream;
 use aws_sdk_s3::Client as S3Client;
@@ -10,36 +10,52 @@ use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::env;
 use std::fs;
-use std::fs::File;
-use std::io::Read;
 use std::path::PathBuf;
 use std::str;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::thread;
 use std::time::Duration;
 use std::time::Instant;
 use std::vec;
 use tokio::sync::broadcast::Sender;
+use tokio::sync::Semaphore;
+use tokio::task::JoinHandle;
 use tokio::time::timeout;
-use wasi_preview1_component_adapter_provider::WASI_SNAPSHOT_PREVIEW1_ADAPTER_NAME;
-use wasi_preview1_component_adapter_provider::WASI_SNAPSHOT_PREVIEW1_COMMAND_ADAPTER;
-use wasmtime::component::{Component, Linker, ResourceTable};
-use wasmtime::StoreLimits;
-use wasmtime::StoreLimitsBuilder;
-use wasmtime::{Config, Engine, Store};
-use wasmtime_wasi::bindings::Command;
+use wasmtime::component::{InstancePre, Linker, ResourceTable};
+use wasmtime::{Config, Engine, Store, Trap, UpdateDeadline};
+use wasmtime_wasi::bindings::CommandPre;
 use wasmtime_wasi::pipe::MemoryInputPipe;
 use wasmtime_wasi::pipe::MemoryOutputPipe;
 use wasmtime_wasi::{DirPerms, FilePerms, WasiCtx, WasiCtxBuilder, WasiView};
 
+use crate::bot_archive;
+use crate::component_cache::ComponentCache;
+use crate::game::{with_game, Game, GameKind};
+use crate::history;
+use crate::interpreters::{InterpreterRegistry, Preopen};
+use crate::leaderboard::{self, MatchResult};
+use crate::limits::{BotLimiter, BotLimits, LimitReached, LimitsConfig, ResourceUsage};
+use crate::player;
+use crate::prisoners_dilemma::PrisonersDilemma;
+use crate::rps::RockPaperScissors;
+use crate::rpsls::RockPaperScissorsLizardSpock;
+use crate::seed;
+use crate::session::BotSession;
+use crate::standings::{self, StandingsEntry, StandingsUpdate};
 use crate::ConnectionPool;
 
-const STDOUT_STDERR_LIMIT: usize = 100 * 1024; // 100KiB
-const WASM_TIMEOUT_LIMIT: Duration = Duration::from_millis(1000);
-const WASM_MAX_FUEL: u64 = 1_000_000_000;
+/// How often the engine's epoch advances. Running bots yield and have their deadlines
+/// checked on every tick.
+const EPOCH_TICK: Duration = Duration::from_millis(10);
+/// Bots are normally stopped by their epoch deadline, but one that's waiting on the host
+/// (e.g. sleeping) isn't running Wasm code to be interrupted, so it's timed out from outside.
+pub const TIMEOUT_GRACE: Duration = Duration::from_millis(100);
 
 pub struct ComponentRunStates {
     pub wasi_ctx: WasiCtx,
     pub resource_table: ResourceTable,
-    limits: StoreLimits,
+    pub limiter: BotLimiter,
 }
 
 impl WasiView for ComponentRunStates {
@@ -51,41 +67,85 @@ impl WasiView for ComponentRunStates {
     }
 }
 
-#[derive(Serialize, Clone, PartialEq, Eq)]
-pub enum SPROption {
-    Scissors = 0,
-    Paper,
-    Rock,
-    Invalid,
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub enum BotRunType {
+    Wasi = 1,
+    Python,
+    /// A component exporting the `snippy:bot/player` world from `wit/player.wit`.
+    Component,
+    JavaScript,
+    /// Source code for any other language in the interpreter registry.
+    Script,
 }
 
-impl SPROption {
-    fn beats(&self, other: &SPROption) -> bool {
+impl BotRunType {
+    pub fn to_db_value(&self) -> i32 {
         match self {
-            SPROption::Scissors => match other {
-                SPROption::Paper => true,
-                SPROption::Invalid => true,
-                _ => false,
-            },
-            SPROption::Paper => match other {
-                SPROption::Rock => true,
-                SPROption::Invalid => true,
-                _ => false,
-            },
-            SPROption::Rock => match other {
-                SPROption::Scissors => true,
-                SPROption::Invalid => true,
-                _ => false,
-            },
-            _ => false,
+            BotRunType::Wasi => 1,
+            BotRunType::Python => 2,
+            BotRunType::Component => 3,
+            BotRunType::JavaScript => 4,
+            BotRunType::Script => 5,
+        }
+    }
+
+    pub fn from_db_value(value: i32) -> BotRunType {
+        match value {
+            1 => BotRunType::Wasi,
+            2 => BotRunType::Python,
+            3 => BotRunType::Component,
+            4 => BotRunType::JavaScript,
+            5 => BotRunType::Script,
+            _ => BotRunType::Python,
+        }
+    }
+
+    /// Script bots in a language that has its own run type are stored as that type, so
+    /// they're listed the same way as before languages came from the registry.
+    pub fn for_language(self, language: &String) -> BotRunType {
+        match (self, language.as_str()) {
+            (BotRunType::Script, "Python") => BotRunType::Python,
+            (BotRunType::Script, "JavaScript") => BotRunType::JavaScript,
+            (run_type, _) => run_type,
         }
     }
 }
 
-#[derive(Clone, Debug, Deserialize, Serialize)]
-pub enum BotRunType {
-    Wasi = 1,
-    Python,
+/// Whether a bot's Wasm file is a core module (built for WASI preview1) or a component.
+#[derive(Clone, Copy, Debug, Default, Serialize, PartialEq, Eq)]
+pub enum WasmKind {
+    #[default]
+    CoreModule,
+    Component,
+}
+
+impl WasmKind {
+    /// Core modules and components start with the same `\0asm` magic number, followed by
+    /// a version (core modules) or a version and layer (components).
+    pub fn detect(bytes: &[u8]) -> Option<WasmKind> {
+        if bytes.len() < 8 || &bytes[0..4] != b"\0asm" {
+            return None;
+        }
+        match bytes[4..8] {
+            [1, 0, 0, 0] => Some(WasmKind::CoreModule),
+            [_, _, 1, 0] => Some(WasmKind::Component),
+            _ => None,
+        }
+    }
+
+    pub fn to_db_value(&self) -> i32 {
+        match self {
+            WasmKind::CoreModule => 1,
+            WasmKind::Component => 2,
+        }
+    }
+
+    pub fn from_db_value(value: i32) -> WasmKind {
+        match value {
+            2 => WasmKind::Component,
+            _ => WasmKind::CoreModule,
+        }
+    }
 }
 
 #[derive(Clone, Serialize, Debug)]
@@ -96,16 +156,41 @@ pub struct BotDetails {
     pub code: String,
     pub wasm_path: String,
     pub wasm_bytes: Option<Vec<u8>>,
+    pub wasm_kind: WasmKind,
+    /// Session bots keep running for a whole match and are sent each round on stdin.
+    pub session: bool,
+    /// The interpreter that runs a Script bot's code.
+    pub language: String,
+    /// The games the bot can play. It's only entered in tournaments of these games.
+    pub games: Vec<GameKind>,
+}
+
+impl BotDetails {
+    /// Component bots are called once per round, so they can't run as a session.
+    pub fn uses_session(&self) -> bool {
+        self.session && !matches!(self.run_type, BotRunType::Component)
+    }
+
+    /// The name of the interpreter that runs the bot, for bots submitted as source code.
+    pub fn language(&self) -> Option<&str> {
+        match self.run_type {
+            BotRunType::Python => Some("Python"),
+            BotRunType::JavaScript => Some("JavaScript"),
+            BotRunType::Script => Some(&self.language),
+            BotRunType::Wasi | BotRunType::Component => None,
+        }
+    }
 }
 
 pub struct WasmRuntime {
-    engine: Engine,
+    pub engine: Engine,
     linker: Linker<ComponentRunStates>,
-    python_component: Component,
+    component_cache: ComponentCache,
+    pub interpreters: InterpreterRegistry,
 }
 
 lazy_static! {
-    static ref WASM_RUNTIME: WasmRuntime = {
+    pub static ref WASM_RUNTIME: WasmRuntime = {
         let runtime = WasmRuntime::new().unwrap();
         runtime
     };
@@ -120,112 +205,243 @@ impl WasmRuntime {
         config.consume_fuel(true);
         config.async_support(true);
         config.wasm_threads(false);
+        config.epoch_interruption(true);
         let engine = Engine::new(&config)?;
 
+        let ticker_engine = engine.clone();
+        thread::spawn(move || loop {
+            thread::sleep(EPOCH_TICK);
+            ticker_engine.increment_epoch();
+        });
+
         // Prepare a linker with the WASI p2 modules.
         let mut linker: Linker<ComponentRunStates> = Linker::new(&engine);
         wasmtime_wasi::add_to_linker_async(&mut linker)?;
 
-        println!("Pre-loading Python interpreter component...");
-        let mut python_wasm_bytes = vec![];
-        File::open("./python-3.11.4.wasm")?.read_to_end(&mut python_wasm_bytes)?;
-        let component_bytes = wit_component::ComponentEncoder::default()
-            .module(&python_wasm_bytes)?
-            .adapter(
-                WASI_SNAPSHOT_PREVIEW1_ADAPTER_NAME,
-                WASI_SNAPSHOT_PREVIEW1_COMMAND_ADAPTER,
-            )?
-            .encode()?;
-
-        let component = Component::from_binary(&engine, &component_bytes)?;
+        // Not the shared temp directory, where other users could plant artifacts.
+        let cache_dir = match env::var("COMPONENT_CACHE_DIR") {
+            Ok(cache_dir) => PathBuf::from(cache_dir),
+            Err(_) => env::var("XDG_CACHE_HOME")
+                .map(PathBuf::from)
+                .or_else(|_| env::var("HOME").map(|home| PathBuf::from(home).join(".cache")))
+                .unwrap_or(env::temp_dir())
+                .join("snippy-component-cache"),
+        };
+        let component_cache = ComponentCache::new(cache_dir);
+
+        let interpreters_config = env::var("INTERPRETERS_CONFIG")
+            .map(PathBuf::from)
+            .unwrap_or(PathBuf::from("./interpreters.json"));
+        let interpreters =
+            InterpreterRegistry::load(&engine, &linker, &component_cache, &interpreters_config)?;
+
         let duration = start.elapsed();
         println!("Loaded Wasm engine in {}s", duration.as_secs_f32());
 
         Ok(WasmRuntime {
             engine: engine,
             linker: linker,
-            python_component: component,
+            component_cache: component_cache,
+            interpreters: interpreters,
         })
     }
 }
 
 #[derive(Serialize)]
-pub struct BotRunResult {
+pub struct BotRunResult<M> {
     pub stdin: String,
     pub stdout: String,
     pub stderr: String,
     pub duration: f32,
-    pub result: SPROption,
+    pub result: M,
     pub invalid_reason: Option<String>,
+    /// Debug information returned by component bots along with their play.
+    pub debug: Vec<DebugEntry>,
+    /// What the bot said about its play, if it printed it as JSON.
+    pub annotation: Option<MoveAnnotation>,
+    /// Only measured for bots that start fresh for the run, not for session bots.
+    pub usage: Option<ResourceUsage>,
 }
 
 #[derive(Serialize)]
-pub struct BotMatchOutcome {
+pub struct DebugEntry {
+    pub key: String,
+    pub value: String,
+}
+
+/// A bot's own comments on a play, shown to spectators next to it.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct MoveAnnotation {
+    /// How sure the bot is of its play, from 0 to 1.
+    pub confidence: Option<f32>,
+    pub note: Option<String>,
+}
+
+/// Notes longer than this are cut short.
+const MAX_NOTE_CHARS: usize = 280;
+
+/// A play printed as a JSON object instead of plain text, e.g.
+/// `{"move": "rock", "confidence": 0.7, "note": "They always open with scissors"}`.
+/// The annotations are read as any JSON value, so that one the bot got wrong is dropped
+/// instead of making the whole play invalid.
+#[derive(Deserialize)]
+struct JsonPlay {
+    #[serde(rename = "move")]
+    play: String,
+    #[serde(default)]
+    confidence: serde_json::Value,
+    #[serde(default)]
+    note: serde_json::Value,
+}
+
+#[derive(Serialize)]
+pub struct BotMatchOutcome<M> {
     pub round: u32,
-    pub play: SPROption,
+    pub play: M,
     pub opponent: String,
-    pub opponent_play: SPROption,
+    pub opponent_play: M,
 }
 
-#[derive(Serialize)]
-pub struct BotRunInput {
-    botname: String,
-    opponent: String,
-    round: u32,
-    history: Vec<SPROption>,
+/// Version of the JSON input given to bots on stdin. Fields are only ever added, so bots
+/// written against an older version keep working.
+const BOT_INPUT_VERSION: u32 = 3;
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub enum RoundResult {
+    Win,
+    Loss,
+    Draw,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+pub struct Score {
+    pub wins: u32,
+    pub losses: u32,
+    pub draws: u32,
+}
+
+/// Where a bot run is taking place, so bots can tell matches apart.
+#[derive(Clone, Default)]
+pub struct MatchContext {
+    pub match_id: Option<String>,
+    pub tournament_id: Option<i32>,
 }
 
-fn generate_stdin_input(
+#[derive(Serialize, Deserialize)]
+pub struct BotRunInput<M> {
+    pub version: u32,
+    /// Added in version 3. Inputs written for older versions are rock-paper-scissors.
+    #[serde(default)]
+    pub game: GameKind,
+    pub botname: String,
+    pub opponent: String,
+    pub round: u32,
+    /// This bot's own plays so far in the match.
+    pub history: Vec<M>,
+    pub opponent_history: Vec<M>,
+    pub results: Vec<RoundResult>,
+    pub score: Score,
+    pub match_id: Option<String>,
+    pub tournament_id: Option<i32>,
+}
+
+fn generate_stdin_input<G: Game>(
+    game: &G,
     bot_name: &String,
     opponent_name: &String,
-    history: &Vec<SPROption>,
+    history: &Vec<G::Move>,
+    opponent_history: &Vec<G::Move>,
+    context: &MatchContext,
 ) -> String {
+    let results: Vec<RoundResult> = history
+        .iter()
+        .zip(opponent_history.iter())
+        .map(|(play, opponent_play)| game.round_result(play, opponent_play))
+        .collect();
+    let mut score = Score::default();
+    for result in &results {
+        match result {
+            RoundResult::Win => score.wins += 1,
+            RoundResult::Loss => score.losses += 1,
+            RoundResult::Draw => score.draws += 1,
+        }
+    }
+
     let input = BotRunInput {
+        version: BOT_INPUT_VERSION,
+        game: game.kind(),
         botname: bot_name.clone(),
         opponent: opponent_name.clone(),
         round: history.len() as u32,
         history: history.clone(),
+        opponent_history: opponent_history.clone(),
+        results,
+        score,
+        match_id: context.match_id.clone(),
+        tournament_id: context.tournament_id,
     };
 
-    serde_json::to_string(&input).unwrap()
+    game.bot_input(&input)
 }
 
-pub async fn run_bot(
+#[allow(clippy::too_many_arguments)]
+pub async fn run_bot<G: Game>(
+    game: &G,
     bot_details: &BotDetails,
     opponent_name: &String,
-    history: &Vec<SPROption>,
-) -> BotRunResult {
-    let input = generate_stdin_input(&bot_details.name, opponent_name, &history);
-
-    match bot_details.run_type {
-        BotRunType::Wasi => {
-            return run_wasi_bot(&bot_details, input).await;
-        }
-        BotRunType::Python => {
-            return run_python_bot(&bot_details, input).await;
-        }
-    }
+    history: &Vec<G::Move>,
+    opponent_history: &Vec<G::Move>,
+    context: &MatchContext,
+    limits: &BotLimits,
+    seed: u64,
+) -> BotRunResult<G::Move> {
+    let input = generate_stdin_input(
+        game,
+        &bot_details.name,
+        opponent_name,
+        history,
+        opponent_history,
+        context,
+    );
+
+    return run_bot_once(game, bot_details, input, limits, seed).await;
 }
 
-pub async fn test_bot(bot_details: &BotDetails, stdin: Option<String>) -> BotRunResult {
+pub async fn test_bot<G: Game>(
+    game: &G,
+    bot_details: &BotDetails,
+    stdin: Option<String>,
+    limits_config: &LimitsConfig,
+) -> BotRunResult<G::Move> {
+    let limits = &limits_config.for_bot(bot_details);
+    // Test runs aren't part of a tournament, so they get a seed of their own.
+    let seed = rand::random();
     let bot_details = bot_details.clone();
     let input = match stdin {
         Some(stdin) => stdin,
         None => {
-            let test_history = vec![SPROption::Rock, SPROption::Scissors];
+            // e.g. Rock, Scissors against Paper, Scissors.
+            let moves = game.moves();
+            let last_move = moves[moves.len() - 1].clone();
+            let test_history = vec![moves[0].clone(), last_move.clone()];
+            let test_opponent_history = vec![moves[1 % moves.len()].clone(), last_move];
             let test_opponent = "testbot".to_string();
-            generate_stdin_input(&bot_details.name, &test_opponent, &test_history)
+            generate_stdin_input(
+                game,
+                &bot_details.name,
+                &test_opponent,
+                &test_history,
+                &test_opponent_history,
+                &MatchContext::default(),
+            )
         }
     };
 
-    match bot_details.run_type {
-        BotRunType::Wasi => {
-            return run_wasi_bot(&bot_details, input).await;
-        }
-        BotRunType::Python => {
-            return run_python_bot(&bot_details, input).await;
-        }
+    if bot_details.uses_session() {
+        let mut session = BotSession::start(&bot_details, limits, seed).await;
+        return session.play_round(game, input).await;
     }
+    return run_bot_once(game, &bot_details, input, limits, seed).await;
 }
 
 pub async fn add_bot(
@@ -233,27 +449,39 @@ pub async fn add_bot(
     bucket_name: &String,
     bot_details: &mut BotDetails,
     test: bool,
+    config: &TournamentConfig,
 ) -> Result<u64> {
     if test {
-        test_bot(&bot_details, None).await;
+        for game_kind in &bot_details.games {
+            let config = TournamentConfig {
+                game: *game_kind,
+                ..config.clone()
+            };
+            with_game!(config, game => {
+                test_bot(&game, &bot_details, None, &config.limits).await;
+            });
+        }
     }
 
     let wasm_path = match bot_details.wasm_bytes.clone() {
         None => bot_details.wasm_path.clone(),
         Some(bytes) => {
-            // Upload to S3
-            save_bot_code(&bucket_name, bytes).await?
+            // Upload to S3. Uploaded bots in an interpreted language are zip archives.
+            save_bot_code(&bucket_name, bytes, bot_details.language().is_some()).await?
         }
     };
 
     bot_details.wasm_path = wasm_path.clone();
 
     let conn = db_pool.get().await?;
-    let stmt = conn.prepare("INSERT INTO bots (name, script_contents, run_type, wasm_path) VALUES ($1, $2, $3, $4) ON CONFLICT DO NOTHING").await?;
-    let run_type: i32 = match bot_details.run_type {
-        BotRunType::Wasi => 1,
-        BotRunType::Python => 2,
-    };
+    let stmt = conn.prepare("INSERT INTO bots (name, script_contents, run_type, wasm_path, is_session, wasm_kind, language, games) VALUES ($1, $2, $3, $4, $5, $6, $7, $8) ON CONFLICT DO NOTHING").await?;
+    let run_type: i32 = bot_details.run_type.to_db_value();
+    let wasm_kind: i32 = bot_details.wasm_kind.to_db_value();
+    let games: Vec<i32> = bot_details
+        .games
+        .iter()
+        .map(|game| game.to_db_value())
+        .collect();
     let count = conn
         .execute(
             &stmt,
@@ -262,54 +490,207 @@ pub async fn add_bot(
                 &bot_details.code,
                 &run_type,
                 &bot_details.wasm_path,
+                &bot_details.session,
+                &wasm_kind,
+                &bot_details.language,
+                &games,
             ],
         )
         .await?;
     return Ok(count);
 }
 
-fn load_wasi_preview1_module_as_component(engine: &Engine, bytes: &[u8]) -> Result<Component> {
-    let component_bytes = wit_component::ComponentEncoder::default()
-        .module(bytes)?
-        .adapter(
-            WASI_SNAPSHOT_PREVIEW1_ADAPTER_NAME,
-            WASI_SNAPSHOT_PREVIEW1_COMMAND_ADAPTER,
-        )?
-        .encode()?;
-    return Component::from_binary(&engine, &component_bytes);
+/// Bot code is stored in the bucket under its sha256, with a `.wasm` or `.zip` extension,
+/// so the key doubles as the cache key. Bots that haven't been uploaded yet are hashed the
+/// same way.
+fn wasm_hash(bot_details: &BotDetails, bytes: &[u8]) -> String {
+    let path = &bot_details.wasm_path;
+    match path
+        .strip_suffix(".wasm")
+        .or_else(|| path.strip_suffix(".zip"))
+    {
+        Some(hash) if !hash.is_empty() => hash.to_string(),
+        _ => sha256::digest(bytes),
+    }
+}
+
+/// A bot's component, ready to run, with the directories it gets if it needs files.
+pub struct LoadedBot {
+    pub component: InstancePre<ComponentRunStates>,
+    pub args: Vec<String>,
+    pub preopens: Vec<Preopen>,
+    /// Kept until the bot has stopped, since it's one of the bot's preopened directories.
+    pub run_dir: Option<BotRunDir>,
+}
+
+/// Loads what's needed to run a bot. Errors are messages that can be shown to the bot's author.
+pub fn load_bot_component(bot_details: &BotDetails) -> std::result::Result<LoadedBot, String> {
+    match bot_details.run_type {
+        BotRunType::Wasi => load_wasi_bot(bot_details),
+        BotRunType::Component => load_component_bot(bot_details),
+        BotRunType::Python | BotRunType::JavaScript | BotRunType::Script => {
+            load_interpreted_bot(bot_details)
+        }
+    }
 }
 
-async fn run_wasi_bot(bot_details: &BotDetails, input: String) -> BotRunResult {
+fn load_wasi_bot(bot_details: &BotDetails) -> std::result::Result<LoadedBot, String> {
     println!("Running WASI bot, path: {}", bot_details.wasm_path);
-    let args: &[String] = &["wasmbot".to_string()];
-    let component = match bot_details.wasm_bytes.clone() {
+    let bytes = match &bot_details.wasm_bytes {
+        Some(bytes) => bytes,
         None => {
             println!("Error loading module: No wasm bytes found");
+            return Err("Error loading wasm module".to_string());
+        }
+    };
+    let hash = wasm_hash(bot_details, bytes);
+    let component = match bot_details.wasm_kind {
+        WasmKind::CoreModule => WASM_RUNTIME.component_cache.get_or_load(
+            &WASM_RUNTIME.engine,
+            &WASM_RUNTIME.linker,
+            &hash,
+            bytes,
+        ),
+        WasmKind::Component => WASM_RUNTIME.component_cache.get_or_load_component(
+            &WASM_RUNTIME.engine,
+            &WASM_RUNTIME.linker,
+            &hash,
+            bytes,
+        ),
+    }
+    .map_err(|e| {
+        println!("Error loading module: {}", e);
+        "Error loading wasm module".to_string()
+    })?;
+    return Ok(LoadedBot {
+        component,
+        args: vec!["wasmbot".to_string()],
+        preopens: vec![],
+        run_dir: None,
+    });
+}
+
+fn load_component_bot(bot_details: &BotDetails) -> std::result::Result<LoadedBot, String> {
+    println!("Running component bot, path: {}", bot_details.wasm_path);
+    let bytes = match &bot_details.wasm_bytes {
+        Some(bytes) => bytes,
+        None => {
+            println!("Error loading component: No wasm bytes found");
+            return Err("Error loading wasm component".to_string());
+        }
+    };
+    let component = WASM_RUNTIME
+        .component_cache
+        .get_or_load_component(
+            &WASM_RUNTIME.engine,
+            &WASM_RUNTIME.linker,
+            &wasm_hash(bot_details, bytes),
+            bytes,
+        )
+        .map_err(|e| {
+            println!("Error loading component: {}", e);
+            "Error loading wasm component".to_string()
+        })?;
+    return Ok(LoadedBot {
+        component,
+        args: vec!["wasmbot".to_string()],
+        preopens: vec![],
+        run_dir: None,
+    });
+}
+
+/// Writes a bot's source code into its own directory for its language's interpreter to run.
+/// Bots uploaded as a zip archive have the extracted archive mounted instead.
+fn load_interpreted_bot(bot_details: &BotDetails) -> std::result::Result<LoadedBot, String> {
+    let language = bot_details.language().unwrap_or_default();
+    let interpreter = match WASM_RUNTIME.interpreters.get(language) {
+        Some(interpreter) => interpreter,
+        None => {
+            return Err(format!(
+                "{} bots are not available on this server.",
+                language
+            ))
+        }
+    };
+    let config = &interpreter.config;
+    if let Some(bytes) = &bot_details.wasm_bytes {
+        let archive_dir = bot_archive::extract_archive(bytes, &wasm_hash(bot_details, bytes))
+            .map_err(|e| {
+                println!("Error extracting archive: {}", e);
+                "Error extracting the bot's archive".to_string()
+            })?;
+        let mut preopens = vec![Preopen {
+            host_path: archive_dir,
+            guest_path: config.source_dir.clone(),
+        }];
+        preopens.extend(config.preopens.iter().cloned());
+        return Ok(LoadedBot {
+            component: interpreter.component.clone(),
+            args: interpreter.args(),
+            preopens,
+            run_dir: None,
+        });
+    }
+
+    let run_dir = BotRunDir::create()
+        .map_err(|e| format!("Could not create a directory for the bot. Error: {}", e))?;
+    let source_path = run_dir.path.join(&config.source_filename);
+    fs::write(source_path, bot_details.code.as_bytes()).map_err(|e| {
+        format!(
+            "Could write {} to disk. Error: {}",
+            config.source_filename, e
+        )
+    })?;
+    let mut preopens = vec![Preopen {
+        host_path: run_dir.path.clone(),
+        guest_path: config.source_dir.clone(),
+    }];
+    preopens.extend(config.preopens.iter().cloned());
+    return Ok(LoadedBot {
+        component: interpreter.component.clone(),
+        args: interpreter.args(),
+        preopens,
+        run_dir: Some(run_dir),
+    });
+}
+
+/// Runs a bot in a fresh instance, which only lives for this one play.
+async fn run_bot_once<G: Game>(
+    game: &G,
+    bot_details: &BotDetails,
+    input: String,
+    limits: &BotLimits,
+    seed: u64,
+) -> BotRunResult<G::Move> {
+    if let BotRunType::Component = bot_details.run_type {
+        return player::run_player_bot(game, bot_details, input, limits, seed).await;
+    }
+    let loaded = match load_bot_component(bot_details) {
+        Ok(loaded) => loaded,
+        Err(message) => {
             return BotRunResult {
                 stdin: input,
                 stdout: "".to_string(),
                 stderr: "".to_string(),
                 duration: 0.0,
-                result: SPROption::Invalid,
-                invalid_reason: Some("Error loading wasm module".to_string()),
+                result: game.invalid_move(),
+                invalid_reason: Some(message),
+                debug: vec![],
+                annotation: None,
+                usage: None,
             };
         }
-        Some(bytes) => match load_wasi_preview1_module_as_component(&WASM_RUNTIME.engine, &bytes) {
-            Ok(component) => component,
-            Err(e) => {
-                println!("Error loading module: {}", e);
-                return BotRunResult {
-                    stdin: input,
-                    stdout: "".to_string(),
-                    stderr: "".to_string(),
-                    duration: 0.0,
-                    result: SPROption::Invalid,
-                    invalid_reason: Some("Error loading wasm module".to_string()),
-                };
-            }
-        },
     };
-    run_bot_component(&component, args, input, None).await
+    run_bot_component(
+        game,
+        &loaded.component,
+        &loaded.args,
+        input,
+        &loaded.preopens,
+        limits,
+        seed,
+    )
+    .await
 }
 
 fn trim_newlines<'a>(s: &'a str) -> &'a str {
@@ -319,54 +700,134 @@ fn trim_newlines<'a>(s: &'a str) -> &'a str {
     }
 }
 
-fn extract_result_from_stdout(stdout: &String) -> SPROption {
+/// Reads a play from a line of a bot's output, either just the move or a JSON object with
+/// the move and the bot's annotations.
+pub fn parse_play<G: Game>(game: &G, line: &str) -> (G::Move, Option<MoveAnnotation>) {
+    if !line.trim_start().starts_with('{') {
+        return (game.parse_move(line), None);
+    }
+    let json_play = match serde_json::from_str::<JsonPlay>(line) {
+        Ok(json_play) => json_play,
+        Err(_) => return (game.invalid_move(), None),
+    };
+    let annotation = MoveAnnotation {
+        confidence: json_play
+            .confidence
+            .as_f64()
+            .map(|confidence| (confidence as f32).clamp(0.0, 1.0)),
+        note: json_play
+            .note
+            .as_str()
+            .map(|note| note.chars().take(MAX_NOTE_CHARS).collect()),
+    };
+    let annotation = if annotation.confidence.is_some() || annotation.note.is_some() {
+        Some(annotation)
+    } else {
+        None
+    };
+    return (game.parse_move(&json_play.play), annotation);
+}
+
+pub fn extract_result_from_stdout<G: Game>(
+    game: &G,
+    stdout: &String,
+) -> (G::Move, Option<MoveAnnotation>) {
     let lines: Vec<&str> = trim_newlines(stdout).split("\n").collect();
     let last_line = *lines.last().unwrap_or(&"");
-    match last_line.to_lowercase().as_str() {
-        "scissors" => SPROption::Scissors,
-        "paper" => SPROption::Paper,
-        "rock" => SPROption::Rock,
-        _ => SPROption::Invalid,
-    }
+    parse_play(game, last_line)
 }
 
-async fn run_python_bot(bot_details: &BotDetails, input: String) -> BotRunResult {
-    let args: &[String] = &["python".to_string(), "main.py".to_string()];
+/// A private directory holding a single bot run's files, which is mounted as the bot's
+/// root directory. It's deleted again when dropped.
+pub struct BotRunDir {
+    pub path: PathBuf,
+}
 
-    let temp_dir_path = env::temp_dir();
-    let main_py_path = temp_dir_path.join("main.py");
-    match fs::write(main_py_path, bot_details.code.as_bytes()) {
-        Ok(_) => {}
-        Err(e) => {
-            let message = format!("Could write main.py to disk. Error: {}", e);
-            return BotRunResult {
-                stdin: input.clone(),
-                stdout: "".to_string(),
-                stderr: "".to_string(),
-                duration: 0.0,
-                result: SPROption::Invalid,
-                invalid_reason: Some(message),
-            };
+impl BotRunDir {
+    fn create() -> Result<BotRunDir> {
+        let path = env::temp_dir().join(format!("snippy-bot-{:016x}", rand::random::<u64>()));
+        fs::create_dir(&path)?;
+        return Ok(BotRunDir { path });
+    }
+}
+
+impl Drop for BotRunDir {
+    fn drop(&mut self) {
+        if let Err(e) = fs::remove_dir_all(&self.path) {
+            println!("Could not remove {}: {}", self.path.display(), e);
         }
+    }
+}
+
+/// What the bot in the store has used, for a run that started when it was instantiated.
+pub fn resource_usage(
+    store: &Store<ComponentRunStates>,
+    limits: &BotLimits,
+    instantiation_time: Duration,
+) -> ResourceUsage {
+    ResourceUsage {
+        fuel_consumed: limits.fuel - store.get_fuel().unwrap_or(0),
+        peak_memory: store.data().limiter.peak_memory,
+        instantiation_time: instantiation_time.as_secs_f32(),
+        limits: limits.clone(),
+    }
+}
+
+/// Mounts a bot's directories, read-only.
+pub fn add_preopens(
+    wasi_ctx_builder: &mut WasiCtxBuilder,
+    preopens: &[Preopen],
+) -> std::result::Result<(), String> {
+    for preopen in preopens {
+        wasi_ctx_builder
+            .preopened_dir(
+                &preopen.host_path,
+                &preopen.guest_path,
+                DirPerms::READ,
+                FilePerms::READ,
+            )
+            .map_err(|e| format!("Could not set up preopened directory. Error: {}", e))?;
+    }
+    return Ok(());
+}
+
+/// Creates a store for a single bot instance, with its fuel and memory limits. The store's
+/// epoch deadline callback stops the bot once it's over its time limit, and otherwise lets
+/// other tasks run on every epoch tick. Callers can install their own callback instead.
+pub fn new_bot_store(wasi_ctx: WasiCtx, limits: &BotLimits) -> Result<Store<ComponentRunStates>> {
+    let state = ComponentRunStates {
+        wasi_ctx,
+        resource_table: ResourceTable::new(),
+        limiter: BotLimiter::new(limits),
     };
-    run_bot_component(
-        &WASM_RUNTIME.python_component,
-        args,
-        input,
-        Some(temp_dir_path),
-    )
-    .await
+    let mut store = Store::new(&WASM_RUNTIME.engine, state);
+    store.limiter(|state| &mut state.limiter);
+    store.set_fuel(limits.fuel)?;
+
+    let started = Instant::now();
+    let time_limit = limits.time();
+    store.epoch_deadline_callback(move |_| {
+        if started.elapsed() >= time_limit {
+            return Err(Trap::Interrupt.into());
+        }
+        return Ok(UpdateDeadline::Yield(1));
+    });
+    store.set_epoch_deadline(1);
+    return Ok(store);
 }
 
-async fn run_bot_component(
-    component: &Component,
+async fn run_bot_component<G: Game>(
+    game: &G,
+    component: &InstancePre<ComponentRunStates>,
     args: &[String],
     input: String,
-    temp_dir_path: Option<PathBuf>,
-) -> BotRunResult {
+    preopens: &[Preopen],
+    limits: &BotLimits,
+    seed: u64,
+) -> BotRunResult<G::Move> {
     let stdin: MemoryInputPipe = MemoryInputPipe::new(input.clone());
-    let stdout = MemoryOutputPipe::new(STDOUT_STDERR_LIMIT);
-    let stderr = MemoryOutputPipe::new(STDOUT_STDERR_LIMIT);
+    let stdout = MemoryOutputPipe::new(limits.output_bytes());
+    let stderr = MemoryOutputPipe::new(limits.output_bytes());
 
     let mut wasi_ctx_builder = WasiCtxBuilder::new();
     wasi_ctx_builder
@@ -374,76 +835,69 @@ async fn run_bot_component(
         .stdin(stdin)
         .stdout(stdout.clone())
         .stderr(stderr.clone());
+    seed::add_seeded_sources(&mut wasi_ctx_builder, seed);
+
+    if let Err(message) = add_preopens(&mut wasi_ctx_builder, preopens) {
+        return BotRunResult {
+            stdin: input.clone(),
+            stdout: "".to_string(),
+            stderr: "".to_string(),
+            duration: 0.0,
+            result: game.invalid_move(),
+            invalid_reason: Some(message),
+            debug: vec![],
+            annotation: None,
+            usage: None,
+        };
+    }
+    let wasi = wasi_ctx_builder.build();
 
-    let wasi = match temp_dir_path {
-        Some(path) => {
-            let result = wasi_ctx_builder.preopened_dir(path, "/", DirPerms::READ, FilePerms::READ);
-            match result {
-                Ok(builder) => builder.build(),
-                Err(e) => {
-                    let message = format!("Could not set up preopened directory. Error: {}", e);
-                    return BotRunResult {
-                        stdin: input.clone(),
-                        stdout: "".to_string(),
-                        stderr: "".to_string(),
-                        duration: 0.0,
-                        result: SPROption::Invalid,
-                        invalid_reason: Some(message),
-                    };
-                }
-            }
+    let mut store = match new_bot_store(wasi, limits) {
+        Ok(store) => store,
+        Err(e) => {
+            let message = format!("Could not set up the bot's store. Error: {}", e);
+            return BotRunResult {
+                stdin: input.clone(),
+                stdout: "".to_string(),
+                stderr: "".to_string(),
+                duration: 0.0,
+                result: game.invalid_move(),
+                invalid_reason: Some(message),
+                debug: vec![],
+                annotation: None,
+                usage: None,
+            };
         }
-        None => wasi_ctx_builder.build(),
     };
 
-    let state = ComponentRunStates {
-        wasi_ctx: wasi,
-        resource_table: ResourceTable::new(),
-        limits: StoreLimitsBuilder::new()
-            .instances(8)
-            .memories(4)
-            .memory_size(100 << 20 /* 100 MB */)
-            .tables(4)
-            .table_elements(20000)
-            .build(),
+    let start = Instant::now();
+    let command_pre = CommandPre::new(component.clone());
+    let command = match command_pre {
+        Ok(command_pre) => command_pre.instantiate_async(&mut store).await,
+        Err(e) => Err(e),
     };
-
-    let mut store = Store::new(&WASM_RUNTIME.engine, state);
-    store.limiter(|state| &mut state.limits);
-    match store.set_fuel(WASM_MAX_FUEL) {
-        Ok(_) => {}
+    let command = match command {
+        Ok(c) => c,
         Err(e) => {
-            let message = format!("Could not set fuel. Error: {}", e);
+            let message = format!("Could not instantiate Wasm component. Error: {}", e);
             return BotRunResult {
                 stdin: input.clone(),
                 stdout: "".to_string(),
                 stderr: "".to_string(),
                 duration: 0.0,
-                result: SPROption::Invalid,
+                result: game.invalid_move(),
                 invalid_reason: Some(message),
+                debug: vec![],
+                annotation: None,
+                usage: None,
             };
         }
     };
 
-    let start = Instant::now();
-    let command =
-        match Command::instantiate_async(&mut store, component, &WASM_RUNTIME.linker).await {
-            Ok(c) => c,
-            Err(e) => {
-                let message = format!("Could not instantiate Wasm component. Error: {}", e);
-                return BotRunResult {
-                    stdin: input.clone(),
-                    stdout: "".to_string(),
-                    stderr: "".to_string(),
-                    duration: 0.0,
-                    result: SPROption::Invalid,
-                    invalid_reason: Some(message),
-                };
-            }
-        };
+    let instantiation_time = start.elapsed();
 
     let result = timeout(
-        WASM_TIMEOUT_LIMIT,
+        limits.time() + TIMEOUT_GRACE,
         command.wasi_cli_run().call_run(&mut store),
     )
     .await;
@@ -453,24 +907,23 @@ async fn run_bot_component(
 
     let stdout_str = String::from_utf8_lossy(&stdout.contents()).to_string();
     let stderr_str = String::from_utf8_lossy(&stderr.contents()).to_string();
+    let usage = resource_usage(&store, limits, instantiation_time);
 
     match result {
         Ok(Ok(_)) => (),
         Ok(Err(e)) => {
-            let consumed: u64 = WASM_MAX_FUEL - store.get_fuel().unwrap_or(0);
-            let remaining = WASM_MAX_FUEL.checked_sub(consumed).unwrap_or(0);
-            if remaining == 0 {
-                let message = format!(
-                    "Program ran out of fuel: It reached the limit of {} wasm instructions.",
-                    WASM_MAX_FUEL
-                );
+            if let Some(limit) = LimitReached::from_error(&e) {
+                let message = limit.message(limits);
                 return BotRunResult {
                     stdin: input.clone(),
                     stdout: stdout_str,
                     stderr: stderr_str,
                     duration: duration.as_secs_f32(),
-                    result: SPROption::Invalid,
+                    result: game.invalid_move(),
                     invalid_reason: Some(message),
+                    debug: vec![],
+                    annotation: None,
+                    usage: Some(usage),
                 };
             }
             println!("Runtime error: {}", e);
@@ -479,32 +932,34 @@ async fn run_bot_component(
                 stdout: stdout_str,
                 stderr: stderr_str,
                 duration: duration.as_secs_f32(),
-                result: SPROption::Invalid,
+                result: game.invalid_move(),
                 invalid_reason: Some("Program did not exit successfully.".to_string()),
+                debug: vec![],
+                annotation: None,
+                usage: Some(usage),
             };
         }
         Err(_) => {
-            let nice_message = format!(
-                "Timeout! Bots are limited to {}ms",
-                WASM_TIMEOUT_LIMIT.as_millis()
-            );
+            let nice_message = LimitReached::Time.message(limits);
             return BotRunResult {
                 stdin: input.clone(),
                 stdout: stdout_str,
                 stderr: stderr_str,
                 duration: duration.as_secs_f32(),
-                result: SPROption::Invalid,
+                result: game.invalid_move(),
                 invalid_reason: Some(nice_message),
+                debug: vec![],
+                annotation: None,
+                usage: Some(usage),
             };
         }
     };
 
-    let bot_result = extract_result_from_stdout(&stdout_str);
-    let invalid_reason = match bot_result {
-        SPROption::Invalid => {
-            Some("Program did not print a valid play on the last line.".to_string())
-        }
-        _ => None,
+    let (bot_result, annotation) = extract_result_from_stdout(game, &stdout_str);
+    let invalid_reason = if bot_result == game.invalid_move() {
+        Some("Program did not print a valid play on the last line.".to_string())
+    } else {
+        None
     };
     return BotRunResult {
         stdin: input.clone(),
@@ -513,17 +968,37 @@ async fn run_bot_component(
         duration: duration.as_secs_f32(),
         result: bot_result,
         invalid_reason: invalid_reason,
+        debug: vec![],
+        annotation,
+        usage: Some(usage),
     };
 }
 
-async fn get_bots(db_pool: &ConnectionPool, bucket_name: &String) -> Result<Vec<BotDetails>> {
+/// The bots entering a tournament: the ones in the config, even if they've been disabled
+/// since, or else every bot that's enabled. Only bots that can play the tournament's game
+/// enter. They're always in the same order, so that a seeded shuffle of the same bots gives
+/// the same bracket.
+async fn get_bots(
+    db_pool: &ConnectionPool,
+    bucket_name: &String,
+    config: &TournamentConfig,
+) -> Result<Vec<BotDetails>> {
     let conn = db_pool.get().await?;
-    let stmt = conn.prepare("SELECT id, name, script_contents, run_type, wasm_path FROM bots WHERE is_disabled = false OR is_builtin = true").await?;
+    let game = config.game.to_db_value();
+    let rows = match &config.bot_ids {
+        Some(bot_ids) => {
+            let stmt = conn.prepare("SELECT id, name, script_contents, run_type, wasm_path, is_session, wasm_kind, language, games FROM bots WHERE id = ANY($1) AND $2 = ANY(games) ORDER BY id").await?;
+            conn.query(&stmt, &[bot_ids, &game]).await?
+        }
+        None => {
+            let stmt = conn.prepare("SELECT id, name, script_contents, run_type, wasm_path, is_session, wasm_kind, language, games FROM bots WHERE (is_disabled = false OR is_builtin = true) AND $1 = ANY(games) ORDER BY id").await?;
+            conn.query(&stmt, &[&game]).await?
+        }
+    };
 
     let shared_config = aws_config::load_defaults(BehaviorVersion::v2024_03_28()).await;
     let client = S3Client::new(&shared_config);
 
-    let rows = conn.query(&stmt, &[]).await?;
     let mut bots: Vec<BotDetails> = rows
         .iter()
         .map(|row| {
@@ -532,12 +1007,12 @@ async fn get_bots(db_pool: &ConnectionPool, bucket_name: &String) -> Result<Vec<
             let script_contents: String = row.get(2);
             let run_type: i32 = row.get(3);
             let wasm_path: String = row.get(4);
+            let session: bool = row.get(5);
+            let wasm_kind: i32 = row.get(6);
+            let language: String = row.get(7);
+            let games: Vec<i32> = row.get(8);
 
-            let run_type = match run_type {
-                1 => BotRunType::Wasi,
-                2 => BotRunType::Python,
-                _ => BotRunType::Python,
-            };
+            let run_type = BotRunType::from_db_value(run_type);
             BotDetails {
                 id: Some(id),
                 run_type,
@@ -545,6 +1020,10 @@ async fn get_bots(db_pool: &ConnectionPool, bucket_name: &String) -> Result<Vec<
                 code: script_contents,
                 wasm_path,
                 wasm_bytes: None,
+                wasm_kind: WasmKind::from_db_value(wasm_kind),
+                session,
+                language,
+                games: games.into_iter().map(GameKind::from_db_value).collect(),
             }
         })
         .collect();
@@ -579,51 +1058,365 @@ pub enum MatchState {
     Finished,
 }
 
+impl MatchState {
+    pub fn to_db_value(&self) -> i32 {
+        match self {
+            MatchState::NotStarted => 0,
+            MatchState::Bye => 1,
+            MatchState::InProgress => 2,
+            MatchState::Finished => 3,
+        }
+    }
+
+    pub fn from_db_value(value: i32) -> MatchState {
+        match value {
+            1 => MatchState::Bye,
+            2 => MatchState::InProgress,
+            3 => MatchState::Finished,
+            _ => MatchState::NotStarted,
+        }
+    }
+}
+
+/// Which part of a bracket a match belongs to. Only double elimination uses anything
+/// other than `Upper`.
+#[derive(Clone, Copy, Debug, Serialize, PartialEq, Eq)]
+pub enum Bracket {
+    Upper,
+    Lower,
+    GrandFinal,
+    GrandFinalReset,
+}
+
+impl Bracket {
+    pub fn to_db_value(&self) -> i32 {
+        match self {
+            Bracket::Upper => 0,
+            Bracket::Lower => 1,
+            Bracket::GrandFinal => 2,
+            Bracket::GrandFinalReset => 3,
+        }
+    }
+
+    pub fn from_db_value(value: i32) -> Bracket {
+        match value {
+            1 => Bracket::Lower,
+            2 => Bracket::GrandFinal,
+            3 => Bracket::GrandFinalReset,
+            _ => Bracket::Upper,
+        }
+    }
+}
+
 #[derive(Clone, Serialize)]
 pub struct Match {
-    id: String,
-    tournament_round_text: String,
-    next_match_id: Option<String>,
-    participants: Vec<BotDetails>,
-    state: MatchState,
+    pub id: String,
+    pub tournament_round_text: String,
+    /// Where the winner of this match plays next.
+    pub next_match_id: Option<String>,
+    /// Where the loser of this match plays next, if they aren't knocked out.
+    pub next_loser_match_id: Option<String>,
+    pub bracket: Bracket,
+    pub participants: Vec<BotDetails>,
+    pub state: MatchState,
 }
 
 impl Match {
+    fn new_empty(id: String, tournament_round_text: String, bracket: Bracket) -> Match {
+        Match {
+            id,
+            tournament_round_text,
+            next_match_id: None,
+            next_loser_match_id: None,
+            bracket,
+            participants: vec![],
+            state: MatchState::NotStarted,
+        }
+    }
+
     fn set_next_match_id(&mut self, next_match_id: String) {
         self.next_match_id = Some(next_match_id);
     }
+
+    fn set_next_loser_match_id(&mut self, next_loser_match_id: String) {
+        self.next_loser_match_id = Some(next_loser_match_id);
+    }
 }
 
 #[derive(Clone, Serialize)]
-pub struct ParticipantOutcome {
-    name: String,
-    moves: Vec<SPROption>,
-    winner: bool,
+pub struct ParticipantOutcome<M> {
+    pub name: String,
+    pub moves: Vec<M>,
+    /// Fuel used for each move, if it was measured. It isn't for session bots.
+    pub fuel: Vec<Option<u64>>,
+    /// What the bot said about each move.
+    pub annotations: Vec<Option<MoveAnnotation>>,
+    pub winner: bool,
 }
 
 #[derive(Clone, Serialize)]
-pub struct MatchOutcome {
-    match_id: String,
-    state: MatchState,
-    note: Option<String>,
-    winner: usize,
-    participants: Vec<ParticipantOutcome>,
+pub struct MatchOutcome<M> {
+    pub match_id: String,
+    pub state: MatchState,
+    pub note: Option<String>,
+    pub winner: usize,
+    /// Set when neither bot won. Only formats that allow draws leave a match undecided.
+    pub draw: bool,
+    pub participants: Vec<ParticipantOutcome<M>>,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub enum TournamentFormat {
+    SingleElimination,
+    DoubleElimination,
+    RoundRobin,
+    Swiss,
+}
+
+impl TournamentFormat {
+    /// Whether matches may end in a draw, rather than picking a winner to advance.
+    fn allows_draws(&self) -> bool {
+        match self {
+            TournamentFormat::SingleElimination => false,
+            TournamentFormat::DoubleElimination => false,
+            TournamentFormat::RoundRobin => true,
+            TournamentFormat::Swiss => true,
+        }
+    }
+
+    pub fn to_db_value(&self) -> i32 {
+        match self {
+            TournamentFormat::SingleElimination => 1,
+            TournamentFormat::RoundRobin => 2,
+            TournamentFormat::Swiss => 3,
+            TournamentFormat::DoubleElimination => 4,
+        }
+    }
+
+    pub fn from_db_value(value: i32) -> TournamentFormat {
+        match value {
+            2 => TournamentFormat::RoundRobin,
+            3 => TournamentFormat::Swiss,
+            4 => TournamentFormat::DoubleElimination,
+            _ => TournamentFormat::SingleElimination,
+        }
+    }
+}
+
+impl FromStr for TournamentFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<TournamentFormat> {
+        match s {
+            "single_elimination" => Ok(TournamentFormat::SingleElimination),
+            "double_elimination" => Ok(TournamentFormat::DoubleElimination),
+            "round_robin" => Ok(TournamentFormat::RoundRobin),
+            "swiss" => Ok(TournamentFormat::Swiss),
+            _ => Err(anyhow!("Unknown tournament format: {}", s)),
+        }
+    }
+}
+
+/// How a match that's still tied after every round is decided, in formats without draws.
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+pub enum Tiebreak {
+    #[default]
+    CoinToss,
+    /// The bot that used less fuel over the whole match wins. Falls back to a coin toss if
+    /// they used the same, or if either bot's fuel wasn't measured.
+    Fuel,
+    /// The match is played again from a score of 0-0.
+    Replay,
+    /// Extra rounds are played until a bot wins one.
+    SuddenDeath,
+}
+
+impl FromStr for Tiebreak {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Tiebreak> {
+        match s {
+            "coin_toss" => Ok(Tiebreak::CoinToss),
+            "fuel" => Ok(Tiebreak::Fuel),
+            "replay" => Ok(Tiebreak::Replay),
+            "sudden_death" => Ok(Tiebreak::SuddenDeath),
+            _ => Err(anyhow!("Unknown tiebreak: {}", s)),
+        }
+    }
+}
+
+/// A match never lasts more than this many times its number of rounds, however many tied
+/// rounds, replays or sudden death rounds it has. A match still tied then is decided by a
+/// coin toss.
+const MAX_ROUNDS_FACTOR: u32 = 4;
+
+/// How long matches last and how they're won. Anything not set follows the game's rules.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(default)]
+pub struct MatchRules {
+    /// Number of rounds in a match.
+    pub rounds: Option<u32>,
+    /// Score that wins a match as soon as a bot reaches it.
+    pub win_threshold: Option<u32>,
+    /// Whether tied rounds count towards the number of rounds. If not, a match goes on
+    /// until enough rounds have been won.
+    pub ties_count_as_rounds: bool,
+}
+
+impl Default for MatchRules {
+    fn default() -> MatchRules {
+        MatchRules {
+            rounds: None,
+            win_threshold: None,
+            ties_count_as_rounds: true,
+        }
+    }
+}
+
+/// Total fuel used over a match, if it was measured for every move.
+fn total_fuel(fuel: &[Option<u64>]) -> Option<u64> {
+    fuel.iter().copied().sum()
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(default)]
+pub struct TournamentConfig {
+    pub game: GameKind,
+    /// Rules for prisoner's dilemma tournaments.
+    pub prisoners_dilemma: PrisonersDilemma,
+    pub format: TournamentFormat,
+    /// Maximum number of bots running at the same time.
+    pub parallelism: usize,
+    /// Number of rounds to play in a Swiss tournament. Defaults to enough rounds to
+    /// separate the bots, i.e. log2 of the number of bots rounded up.
+    pub swiss_rounds: Option<u32>,
+    pub match_rules: MatchRules,
+    pub tiebreak: Tiebreak,
+    /// Seed for everything random in the tournament: the bracket, coin tosses, and the
+    /// randomness and clocks bots see. Picked when the tournament is created if not set.
+    pub seed: Option<u64>,
+    /// The bots that enter, e.g. to rerun a tournament with the bots that played in it.
+    /// Every enabled bot enters if not set.
+    pub bot_ids: Option<Vec<i32>>,
+    /// Also used for test runs, which don't belong to a tournament.
+    pub limits: LimitsConfig,
+}
+
+impl Default for TournamentConfig {
+    fn default() -> TournamentConfig {
+        TournamentConfig {
+            game: GameKind::default(),
+            prisoners_dilemma: PrisonersDilemma::default(),
+            format: TournamentFormat::SingleElimination,
+            parallelism: thread::available_parallelism().map_or(1, |n| n.get()),
+            swiss_rounds: None,
+            match_rules: MatchRules::default(),
+            tiebreak: Tiebreak::default(),
+            seed: None,
+            bot_ids: None,
+            limits: LimitsConfig::default(),
+        }
+    }
+}
+
+impl TournamentConfig {
+    /// This config with the settings given in `overrides` replaced, e.g.
+    /// `{"format": "RoundRobin", "limits": {"default": {"fuel": 2000000000}}}`. Objects are
+    /// merged field by field, so anything that isn't given stays as it is.
+    pub fn with_overrides(&self, overrides: serde_json::Value) -> Result<TournamentConfig> {
+        let mut config = serde_json::to_value(self)?;
+        merge_json(&mut config, overrides);
+        let config: TournamentConfig = serde_json::from_value(config)?;
+        if config.match_rules.rounds == Some(0) {
+            return Err(anyhow!("match_rules.rounds must be at least 1."));
+        }
+        return Ok(config);
+    }
+}
+
+fn merge_json(base: &mut serde_json::Value, overrides: serde_json::Value) {
+    match (base, overrides) {
+        (serde_json::Value::Object(base), serde_json::Value::Object(overrides)) => {
+            for (key, value) in overrides {
+                merge_json(base.entry(key).or_insert(serde_json::Value::Null), value);
+            }
+        }
+        (base, overrides) => *base = overrides,
+    }
 }
 
 #[derive(Clone, Serialize)]
-pub struct Tournament {
-    starting_matches: Vec<Match>,
-    match_updates: Vec<MatchOutcome>,
+pub struct Tournament<G: Game> {
+    #[serde(skip)]
+    pub game: G,
+    pub id: Option<i32>,
+    pub config: TournamentConfig,
+    pub starting_matches: Vec<Match>,
+    pub match_updates: Vec<MatchOutcome<G::Move>>,
+    pub standings: Vec<StandingsEntry>,
+    #[serde(skip)]
+    pub match_db_ids: HashMap<String, i32>,
 }
 
-impl Tournament {
-    pub fn new() -> Tournament {
+impl<G: Game> Tournament<G> {
+    pub fn new(game: G) -> Tournament<G> {
         Tournament {
+            game,
+            id: None,
+            config: TournamentConfig::default(),
             starting_matches: vec![],
             match_updates: vec![],
+            standings: vec![],
+            match_db_ids: HashMap::new(),
         }
     }
 
+    /// Names of every bot entered in the tournament, in bracket order.
+    pub fn bot_names(&self) -> Vec<String> {
+        let mut names: Vec<String> = vec![];
+        for this_match in &self.starting_matches {
+            for bot in &this_match.participants {
+                if !names.contains(&bot.name) {
+                    names.push(bot.name.clone());
+                }
+            }
+        }
+        names
+    }
+
+    pub fn keeps_standings(&self) -> bool {
+        match self.config.format {
+            TournamentFormat::SingleElimination => false,
+            TournamentFormat::DoubleElimination => false,
+            TournamentFormat::RoundRobin => true,
+            TournamentFormat::Swiss => true,
+        }
+    }
+
+    /// Number of rounds to play in a Swiss tournament. Every bot can play at most
+    /// every other bot once, so this never exceeds the number of bots minus one.
+    fn swiss_rounds(&self) -> u32 {
+        let num_bots = self.bot_names().len() as u32;
+        let default_rounds = (num_bots as f32).log2().ceil() as u32;
+        let max_rounds = num_bots.saturating_sub(1);
+        self.config
+            .swiss_rounds
+            .unwrap_or(default_rounds)
+            .min(max_rounds)
+            .max(1)
+    }
+
+    fn update_standings(&mut self, sender: &Sender<String>) {
+        self.standings =
+            standings::compute_standings(&self.game, &self.bot_names(), &self.match_updates);
+        let update = StandingsUpdate {
+            standings: self.standings.clone(),
+        };
+        sender
+            .send(serde_json::to_string(&update).unwrap())
+            .unwrap();
+    }
+
     pub async fn run(&mut self, sender: Sender<String>, db_pool: &ConnectionPool) -> Result<()> {
         let mut match_participants: HashMap<String, Vec<BotDetails>> = self
             .starting_matches
@@ -631,122 +1424,532 @@ impl Tournament {
             .map(|m| (m.id.clone(), m.participants.clone()))
             .collect();
 
-        for this_match in &self.starting_matches {
-            let participant_outcomes: Vec<ParticipantOutcome> = match_participants
-                .get(&this_match.id)
-                .unwrap()
+        let bots_by_name: HashMap<String, BotDetails> = self
+            .starting_matches
+            .iter()
+            .flat_map(|m| m.participants.iter())
+            .map(|bot| (bot.name.clone(), bot.clone()))
+            .collect();
+        let mut champion: Option<BotDetails> = None;
+        let mut reset_needed = false;
+        let mut swiss_round = 1;
+        let bot_runs = Arc::new(Semaphore::new(self.config.parallelism.max(1)));
+
+        let mut next_match_index = 0;
+        loop {
+            if next_match_index >= self.starting_matches.len() {
+                // Swiss rounds are paired from the standings, so they're only added once the
+                // previous round is over.
+                if self.config.format != TournamentFormat::Swiss
+                    || swiss_round >= self.swiss_rounds()
+                {
+                    break;
+                }
+                swiss_round += 1;
+                let bots: Vec<BotDetails> = self
+                    .bot_names()
+                    .iter()
+                    .filter_map(|name| bots_by_name.get(name).cloned())
+                    .collect();
+                let new_matches =
+                    create_swiss_round(&bots, &self.standings, &self.match_updates, swiss_round);
+                let match_db_ids = history::save_matches(
+                    db_pool,
+                    self.id,
+                    &new_matches,
+                    self.starting_matches.len(),
+                )
+                .await?;
+                self.match_db_ids.extend(match_db_ids);
+                for new_match in &new_matches {
+                    match_participants.insert(new_match.id.clone(), new_match.participants.clone());
+                }
+                self.starting_matches.extend(new_matches);
+                // Send the whole tournament again so clients pick up the new pairings.
+                sender.send(serde_json::to_string(&self).unwrap()).unwrap();
+                continue;
+            }
+
+            // Matches in the same round of the same bracket don't depend on each other, so
+            // they all run at once. They're always next to each other in bracket order.
+            let round_start = next_match_index;
+            let first_match = &self.starting_matches[round_start];
+            let round_end = self.starting_matches[round_start..]
                 .iter()
-                .map(|p| ParticipantOutcome {
-                    name: p.name.clone(),
-                    moves: vec![],
-                    winner: false,
+                .position(|m| {
+                    m.bracket != first_match.bracket
+                        || m.tournament_round_text != first_match.tournament_round_text
                 })
-                .collect();
-            let in_progress_match_out = MatchOutcome {
-                match_id: this_match.id.clone(),
-                state: MatchState::InProgress,
-                winner: 0,
-                note: None,
-                participants: participant_outcomes.clone(),
-            };
-            sender
-                .send(serde_json::to_string(&in_progress_match_out).unwrap())
-                .unwrap();
-            let mut winner_bot = match_participants.get(&this_match.id).unwrap()[0].clone();
-            if this_match.state == MatchState::Bye {
-                let match_out = MatchOutcome {
+                .map(|offset| round_start + offset)
+                .unwrap_or(self.starting_matches.len());
+            let round_matches: Vec<Match> = self.starting_matches[round_start..round_end].to_vec();
+            next_match_index = round_end;
+
+            let mut running_matches: Vec<(Match, Vec<BotDetails>, RunningMatch<G::Move>)> = vec![];
+            for this_match in round_matches {
+                let match_db_id = self.match_db_ids.get(&this_match.id).copied();
+                let participants = match_participants.get(&this_match.id).unwrap().clone();
+                let participant_outcomes: Vec<ParticipantOutcome<G::Move>> = participants
+                    .iter()
+                    .map(|p| ParticipantOutcome {
+                        name: p.name.clone(),
+                        moves: vec![],
+                        fuel: vec![],
+                        annotations: vec![],
+                        winner: false,
+                    })
+                    .collect();
+
+                if this_match.bracket == Bracket::GrandFinalReset && !reset_needed {
+                    let match_out = MatchOutcome {
+                        match_id: this_match.id.clone(),
+                        state: MatchState::Bye,
+                        winner: 0,
+                        draw: false,
+                        note: Some(
+                            "Not needed, the winners' bracket champion won the grand final."
+                                .to_string(),
+                        ),
+                        participants: vec![],
+                    };
+                    sender
+                        .send(serde_json::to_string(&match_out).unwrap())
+                        .unwrap();
+                    history::save_match_outcome(db_pool, match_db_id, &[], &match_out).await?;
+                    running_matches.push((this_match, vec![], RunningMatch::Skipped(match_out)));
+                    continue;
+                }
+
+                let in_progress_match_out = MatchOutcome {
                     match_id: this_match.id.clone(),
-                    state: MatchState::Bye,
+                    state: MatchState::InProgress,
                     winner: 0,
-                    note: Some("Bye".to_string()),
-                    participants: participant_outcomes,
+                    draw: false,
+                    note: None,
+                    participants: participant_outcomes.clone(),
                 };
                 sender
-                    .send(serde_json::to_string(&match_out).unwrap())
-                    .unwrap();
-                self.match_updates.push(match_out);
-            } else {
-                let participants = match_participants.get(&this_match.id).unwrap();
-                let match_outcome = run_match(
-                    &this_match.id,
-                    &participants[0],
-                    &participants[1],
-                    db_pool,
-                    &sender,
-                )
-                .await?;
-                winner_bot = participants[match_outcome.winner as usize].clone();
-                sender
-                    .send(serde_json::to_string(&match_outcome).unwrap())
+                    .send(serde_json::to_string(&in_progress_match_out).unwrap())
                     .unwrap();
-                self.match_updates.push(match_outcome.clone());
+                // Matches can be left short of bots when they're fed by byes in a double elimination bracket.
+                if this_match.state == MatchState::Bye || participants.len() < 2 {
+                    let match_out = MatchOutcome {
+                        match_id: this_match.id.clone(),
+                        state: MatchState::Bye,
+                        winner: 0,
+                        draw: false,
+                        note: Some("Bye".to_string()),
+                        participants: participant_outcomes,
+                    };
+                    sender
+                        .send(serde_json::to_string(&match_out).unwrap())
+                        .unwrap();
+                    history::save_match_outcome(db_pool, match_db_id, &participants, &match_out)
+                        .await?;
+                    running_matches.push((this_match, participants, RunningMatch::Bye(match_out)));
+                } else {
+                    let task = MatchTask(tokio::spawn(run_and_save_match(
+                        self.game.clone(),
+                        this_match.id.clone(),
+                        participants.clone(),
+                        self.config.clone(),
+                        self.id,
+                        match_db_id,
+                        db_pool.clone(),
+                        sender.clone(),
+                        bot_runs.clone(),
+                    )));
+                    running_matches.push((this_match, participants, RunningMatch::Running(task)));
+                }
             }
-            // Add winner to participants for next match.
-            match &this_match.next_match_id {
-                Some(next_match_id) => {
-                    match_participants
-                        .get_mut(next_match_id)
-                        .unwrap()
-                        .push(winner_bot);
+
+            // Handle results in bracket order, so advancing bots land in the same slots as
+            // when matches ran one at a time.
+            for (this_match, participants, running_match) in running_matches {
+                let mut winner_bot = participants.first().cloned();
+                let mut loser_bot: Option<BotDetails> = None;
+                let match_outcome = match running_match {
+                    RunningMatch::Skipped(match_out) => {
+                        self.match_updates.push(match_out);
+                        continue;
+                    }
+                    RunningMatch::Bye(match_out) => match_out,
+                    RunningMatch::Running(mut task) => {
+                        let match_outcome = (&mut task.0).await??;
+                        winner_bot = Some(participants[match_outcome.winner as usize].clone());
+                        loser_bot = Some(participants[1 - match_outcome.winner as usize].clone());
+                        if this_match.bracket == Bracket::GrandFinal {
+                            // The first bot in the grand final is the winners' bracket champion, who
+                            // has to lose twice before being knocked out.
+                            reset_needed = match_outcome.winner != 0;
+                        }
+                        match_outcome
+                    }
+                };
+                self.match_updates.push(match_outcome);
+                if self.keeps_standings() {
+                    self.update_standings(&sender);
                 }
-                None => {
-                    // This is the final match
-                    println!("Winner: {}", winner_bot.name);
+                // Add winner to participants for next match.
+                match &this_match.next_match_id {
+                    Some(next_match_id) => {
+                        if let Some(bot) = winner_bot {
+                            match_participants.get_mut(next_match_id).unwrap().push(bot);
+                        }
+                    }
+                    None => {
+                        if !self.keeps_standings() {
+                            // This is the final match
+                            champion = winner_bot;
+                        }
+                    }
+                }
+                // In double elimination the loser drops into the losers' bracket.
+                if let (Some(next_match_id), Some(bot)) =
+                    (&this_match.next_loser_match_id, loser_bot)
+                {
+                    match_participants.get_mut(next_match_id).unwrap().push(bot);
+                }
+                if this_match.bracket == Bracket::GrandFinal && !reset_needed {
+                    champion = participants.first().cloned();
                 }
             }
         }
+
+        if self.keeps_standings() {
+            champion = self
+                .standings
+                .first()
+                .and_then(|entry| bots_by_name.get(&entry.name))
+                .cloned();
+        }
+        let champion_id = champion.as_ref().and_then(|bot| bot.id);
+        if let Some(champion) = &champion {
+            println!("Winner: {}", champion.name);
+            leaderboard::record_tournament_win(db_pool, champion.id).await?;
+        }
+        history::finish_tournament(db_pool, self.id, champion_id).await?;
         return Ok(());
     }
 }
 
-async fn run_match(
+/// A tournament of whichever game its config chose. It's serialized the same as the
+/// tournament itself.
+#[derive(Clone, Serialize)]
+#[serde(untagged)]
+pub enum GameTournament {
+    RockPaperScissors(Tournament<RockPaperScissors>),
+    RockPaperScissorsLizardSpock(Tournament<RockPaperScissorsLizardSpock>),
+    PrisonersDilemma(Tournament<PrisonersDilemma>),
+}
+
+impl GameTournament {
+    pub async fn run(&mut self, sender: Sender<String>, db_pool: &ConnectionPool) -> Result<()> {
+        match self {
+            GameTournament::RockPaperScissors(tournament) => tournament.run(sender, db_pool).await,
+            GameTournament::RockPaperScissorsLizardSpock(tournament) => {
+                tournament.run(sender, db_pool).await
+            }
+            GameTournament::PrisonersDilemma(tournament) => tournament.run(sender, db_pool).await,
+        }
+    }
+}
+
+impl From<Tournament<RockPaperScissors>> for GameTournament {
+    fn from(tournament: Tournament<RockPaperScissors>) -> GameTournament {
+        GameTournament::RockPaperScissors(tournament)
+    }
+}
+
+impl From<Tournament<RockPaperScissorsLizardSpock>> for GameTournament {
+    fn from(tournament: Tournament<RockPaperScissorsLizardSpock>) -> GameTournament {
+        GameTournament::RockPaperScissorsLizardSpock(tournament)
+    }
+}
+
+impl From<Tournament<PrisonersDilemma>> for GameTournament {
+    fn from(tournament: Tournament<PrisonersDilemma>) -> GameTournament {
+        GameTournament::PrisonersDilemma(tournament)
+    }
+}
+
+/// A match of the current round, either already decided or still being played.
+enum RunningMatch<M> {
+    Skipped(MatchOutcome<M>),
+    Bye(MatchOutcome<M>),
+    Running(MatchTask<Result<MatchOutcome<M>>>),
+}
+
+/// A match, or a bot's run in a round of one, being played in its own task. The task is
+/// stopped if this is dropped before it finishes, e.g. because another match in the round
+/// failed, so it can't go on saving and sending results for a tournament that has already
+/// ended, or hold on to a bot run slot.
+struct MatchTask<T>(JoinHandle<T>);
+
+impl<T> Drop for MatchTask<T> {
+    fn drop(&mut self) {
+        self.0.abort();
+    }
+}
+
+/// Plays a match in its own task, then sends and stores the final result.
+#[allow(clippy::too_many_arguments)]
+async fn run_and_save_match<G: Game>(
+    game: G,
+    match_id: String,
+    participants: Vec<BotDetails>,
+    config: TournamentConfig,
+    tournament_id: Option<i32>,
+    match_db_id: Option<i32>,
+    db_pool: ConnectionPool,
+    sender: Sender<String>,
+    bot_runs: Arc<Semaphore>,
+) -> Result<MatchOutcome<G::Move>> {
+    let match_outcome = run_match(
+        &game,
+        &match_id,
+        &participants[0],
+        &participants[1],
+        &config,
+        tournament_id,
+        match_db_id,
+        &db_pool,
+        &sender,
+        &bot_runs,
+    )
+    .await?;
+    sender
+        .send(serde_json::to_string(&match_outcome).unwrap())
+        .unwrap();
+    history::save_match_outcome(&db_pool, match_db_id, &participants, &match_outcome).await?;
+    return Ok(match_outcome);
+}
+
+/// How a bot plays the rounds of a match: in a fresh instance every round, or in one
+/// session that lasts the whole match.
+enum BotPlayer {
+    PerRound(BotDetails),
+    /// A session that hasn't started yet, and the seed to start it with. It's started by
+    /// the first round, so it only runs while it holds a slot.
+    NewSession(BotDetails, u64),
+    Session(Box<BotSession>),
+}
+
+impl BotPlayer {
+    fn new(bot_details: &BotDetails, seed: u64) -> BotPlayer {
+        if bot_details.uses_session() {
+            return BotPlayer::NewSession(bot_details.clone(), seed);
+        }
+        return BotPlayer::PerRound(bot_details.clone());
+    }
+}
+
+/// Plays a round once a slot is free, so that no more than the configured number of bots
+/// run at the same time. The player is handed back along with the result for the next round.
+#[allow(clippy::too_many_arguments)]
+async fn play_round_when_ready<G: Game>(
+    game: G,
+    bot_runs: Arc<Semaphore>,
+    mut player: BotPlayer,
+    bot_name: String,
+    opponent_name: String,
+    history: Vec<G::Move>,
+    opponent_history: Vec<G::Move>,
+    context: MatchContext,
+    limits: BotLimits,
+    seed: u64,
+) -> (BotPlayer, BotRunResult<G::Move>) {
+    let _permit = bot_runs.acquire_owned().await.unwrap();
+    if let BotPlayer::NewSession(bot_details, session_seed) = &player {
+        let session = BotSession::start(bot_details, &limits, *session_seed).await;
+        player = BotPlayer::Session(Box::new(session));
+    }
+    let result = match &mut player {
+        BotPlayer::PerRound(bot_details) => {
+            run_bot(
+                &game,
+                bot_details,
+                &opponent_name,
+                &history,
+                &opponent_history,
+                &context,
+                &limits,
+                seed,
+            )
+            .await
+        }
+        BotPlayer::Session(session) => {
+            let input = generate_stdin_input(
+                &game,
+                &bot_name,
+                &opponent_name,
+                &history,
+                &opponent_history,
+                &context,
+            );
+            session.play_round(&game, input).await
+        }
+        BotPlayer::NewSession(..) => unreachable!("Sessions are started before their first round."),
+    };
+    return (player, result);
+}
+
+#[allow(clippy::too_many_arguments)]
+async fn run_match<G: Game>(
+    game: &G,
     match_id: &String,
     bot1: &BotDetails,
     bot2: &BotDetails,
+    config: &TournamentConfig,
+    tournament_id: Option<i32>,
+    match_db_id: Option<i32>,
     db_pool: &ConnectionPool,
     sender: &Sender<String>,
-) -> Result<MatchOutcome> {
+    bot_runs: &Arc<Semaphore>,
+) -> Result<MatchOutcome<G::Move>> {
     let match_id = match_id.clone();
     let bot1 = bot1.clone();
     let bot2 = bot2.clone();
 
-    let mut bot1_moves: Vec<SPROption> = vec![];
-    let mut bot2_moves: Vec<SPROption> = vec![];
+    let mut bot1_moves: Vec<G::Move> = vec![];
+    let mut bot2_moves: Vec<G::Move> = vec![];
+    let mut bot1_fuel: Vec<Option<u64>> = vec![];
+    let mut bot2_fuel: Vec<Option<u64>> = vec![];
+    let mut bot1_annotations: Vec<Option<MoveAnnotation>> = vec![];
+    let mut bot2_annotations: Vec<Option<MoveAnnotation>> = vec![];
+
+    let mut bot1_score = 0;
+    let mut bot2_score = 0;
+    let invalid = game.invalid_move();
+
+    let context = MatchContext {
+        match_id: Some(match_id.clone()),
+        tournament_id,
+    };
 
-    let mut bot1_wins = 0;
-    let mut bot2_wins = 0;
+    // Tournaments are always created with a seed.
+    let match_seed = seed::derive_seed(config.seed.unwrap_or_default(), &match_id);
+    let bot1_limits = config.limits.for_bot(&bot1);
+    let bot2_limits = config.limits.for_bot(&bot2);
+    let mut bot1_player = BotPlayer::new(&bot1, seed::derive_seed(match_seed, "bot1/session"));
+    let mut bot2_player = BotPlayer::new(&bot2, seed::derive_seed(match_seed, "bot2/session"));
 
     let mut winner_bot: Option<usize> = None;
-    for _i in 0..5 {
-        let bot1_result = run_bot(&bot1, &bot2.name, &bot1_moves).await;
-        let bot2_result = run_bot(&bot2, &bot1.name, &bot2_moves).await;
+    // Set when both bots made invalid moves in the same round, so neither earned the win.
+    let mut double_forfeit = false;
+    let rules = &config.match_rules;
+    let rounds = rules
+        .rounds
+        .unwrap_or_else(|| game.rounds(seed::derive_seed(match_seed, "rounds")));
+    let win_threshold = rules.win_threshold.or_else(|| game.win_threshold(rounds));
+    // Grows by one for every sudden death round.
+    let mut match_rounds = rounds;
+    let mut counted_rounds = 0;
+    let mut replays = 0;
+    // Number of rounds played when the match was first tied at the end.
+    let mut tied_after: Option<usize> = None;
+    let mut round = 0;
+    while round < rounds.saturating_mul(MAX_ROUNDS_FACTOR) {
+        round += 1;
+        // Both bots play the round at the same time, each in its own task.
+        let mut bot1_task = MatchTask(tokio::spawn(play_round_when_ready(
+            game.clone(),
+            bot_runs.clone(),
+            bot1_player,
+            bot1.name.clone(),
+            bot2.name.clone(),
+            bot1_moves.clone(),
+            bot2_moves.clone(),
+            context.clone(),
+            bot1_limits.clone(),
+            seed::derive_seed(match_seed, &format!("bot1/round-{}", round)),
+        )));
+        let mut bot2_task = MatchTask(tokio::spawn(play_round_when_ready(
+            game.clone(),
+            bot_runs.clone(),
+            bot2_player,
+            bot2.name.clone(),
+            bot1.name.clone(),
+            bot2_moves.clone(),
+            bot1_moves.clone(),
+            context.clone(),
+            bot2_limits.clone(),
+            seed::derive_seed(match_seed, &format!("bot2/round-{}", round)),
+        )));
+        let bot1_result;
+        let bot2_result;
+        // If bot1's run failed, dropping bot2's task stops its run too.
+        (bot1_player, bot1_result) = (&mut bot1_task.0).await?;
+        (bot2_player, bot2_result) = (&mut bot2_task.0).await?;
         let bot1_play = bot1_result.result;
         let bot2_play = bot2_result.result;
         bot1_moves.push(bot1_play.clone());
         bot2_moves.push(bot2_play.clone());
-        if bot1_play == bot2_play {
+        // A session bot's first round also pays for its startup, so its fuel isn't
+        // comparable with other bots' and isn't counted.
+        let bot1_round_fuel = bot1_result
+            .usage
+            .filter(|_| !bot1.uses_session())
+            .map(|usage| usage.fuel_consumed);
+        let bot2_round_fuel = bot2_result
+            .usage
+            .filter(|_| !bot2.uses_session())
+            .map(|usage| usage.fuel_consumed);
+        bot1_fuel.push(bot1_round_fuel);
+        bot2_fuel.push(bot2_round_fuel);
+        history::save_round(
+            game,
+            db_pool,
+            match_db_id,
+            bot1_moves.len() as u32,
+            &[&bot1_play, &bot2_play],
+            &[bot1_round_fuel, bot2_round_fuel],
+            &[&bot1_result.annotation, &bot2_result.annotation],
+        )
+        .await?;
+        bot1_annotations.push(bot1_result.annotation);
+        bot2_annotations.push(bot2_result.annotation);
+        if bot1_play == invalid && bot2_play == invalid {
             // Both invalid, no one wins.
-            if bot1_play == SPROption::Invalid {
-                break;
-            }
-            continue;
-        } else if bot1_play == SPROption::Invalid {
+            println!(
+                "Invalid moves: {:?}, {:?}",
+                bot1_result.invalid_reason, bot2_result.invalid_reason
+            );
+            double_forfeit = true;
+            break;
+        } else if bot1_play == invalid {
             println!("Invalid move: {:?}", bot1_result.invalid_reason);
             winner_bot = Some(1);
             break;
-        } else if bot2_play == SPROption::Invalid {
+        } else if bot2_play == invalid {
             println!("Invalid move: {:?}", bot2_result.invalid_reason);
             winner_bot = Some(0);
             break;
-        } else if bot1_play.beats(&bot2_play) {
-            bot1_wins += 1;
-            if bot1_wins >= 3 {
+        }
+        let (bot1_points, bot2_points) = game.payoff(&bot1_play, &bot2_play);
+        bot1_score += bot1_points;
+        bot2_score += bot2_points;
+        if rules.ties_count_as_rounds || bot1_points != bot2_points {
+            counted_rounds += 1;
+        }
+        if win_threshold.is_some_and(|threshold| bot1_score >= threshold || bot2_score >= threshold)
+        {
+            break;
+        }
+        if counted_rounds >= match_rounds {
+            if bot1_score != bot2_score || config.format.allows_draws() {
                 break;
             }
-        } else {
-            bot2_wins += 1;
-            if bot2_wins >= 3 {
-                break;
+            tied_after.get_or_insert(bot1_moves.len());
+            match config.tiebreak {
+                Tiebreak::SuddenDeath => match_rounds += 1,
+                Tiebreak::Replay => {
+                    replays += 1;
+                    bot1_score = 0;
+                    bot2_score = 0;
+                    counted_rounds = 0;
+                }
+                Tiebreak::CoinToss | Tiebreak::Fuel => break,
             }
         }
 
@@ -754,11 +1957,15 @@ async fn run_match(
             ParticipantOutcome {
                 name: bot1.name.clone(),
                 moves: bot1_moves.clone(),
+                fuel: bot1_fuel.clone(),
+                annotations: bot1_annotations.clone(),
                 winner: false,
             },
             ParticipantOutcome {
                 name: bot2.name.clone(),
                 moves: bot2_moves.clone(),
+                fuel: bot2_fuel.clone(),
+                annotations: bot2_annotations.clone(),
                 winner: false,
             },
         ];
@@ -766,6 +1973,7 @@ async fn run_match(
             match_id: match_id.clone(),
             state: MatchState::InProgress,
             winner: 0,
+            draw: false,
             note: None,
             participants: participant_outcomes.clone(),
         };
@@ -774,49 +1982,125 @@ async fn run_match(
             .unwrap();
     }
 
-    if winner_bot == None {
-        if bot1_wins > bot2_wins {
+    if winner_bot == None && !double_forfeit {
+        if bot1_score > bot2_score {
             winner_bot = Some(0)
-        } else if bot2_wins > bot1_wins {
+        } else if bot2_score > bot1_score {
             winner_bot = Some(1)
         }
     }
 
-    let mut note: Option<String> = None;
+    let note: Option<String>;
+    // A tie broken by fuel counts as a win for the leaderboard, but one broken by a coin
+    // toss is still a draw.
+    let mut match_result = match winner_bot {
+        Some(0) => MatchResult::Bot1Won,
+        Some(_) => MatchResult::Bot2Won,
+        None => MatchResult::Draw,
+    };
+    let draw = winner_bot.is_none() && config.format.allows_draws();
+    let coin_toss = || {
+        let mut rng = seed::seeded_rng(seed::derive_seed(match_seed, "coin-toss"));
+        rng.gen_range(0..2)
+    };
     let winner_bot = match winner_bot {
-        Some(winner_bot) => winner_bot,
+        Some(winner_bot) => {
+            note = match (tied_after, config.tiebreak) {
+                (Some(tied_after), Tiebreak::SuddenDeath) => Some(format!(
+                    "Tied after {} rounds. Won in sudden death.",
+                    tied_after
+                )),
+                (Some(tied_after), Tiebreak::Replay) => Some(format!(
+                    "Tied after {} rounds. Won replay {}.",
+                    tied_after, replays
+                )),
+                _ => None,
+            };
+            winner_bot
+        }
+        None if draw && double_forfeit => {
+            note = Some("Both bots made invalid moves. No result.".to_string());
+            0
+        }
+        None if draw => {
+            note = Some("Draw.".to_string());
+            0
+        }
+        None if double_forfeit => {
+            // Someone has to go through to the next round of the bracket.
+            note =
+                Some("Both bots made invalid moves. No result, advanced by coin toss.".to_string());
+            coin_toss()
+        }
         None => {
-            note = Some("5x Draw. Winner chosen by coin toss.".to_string());
-            // 5 rounds resulted in a draw
-            // Choose random winner - number 0 or 1
-            let mut rng = rand::thread_rng();
-            rng.gen_range(0..2)
+            let fuel = (total_fuel(&bot1_fuel), total_fuel(&bot2_fuel));
+            match (config.tiebreak, fuel) {
+                (Tiebreak::Fuel, (Some(fuel1), Some(fuel2))) if fuel1 != fuel2 => {
+                    note = Some(format!(
+                        "Tied after {} rounds. Winner used fewer instructions ({} vs {}).",
+                        bot1_moves.len(),
+                        fuel1.min(fuel2),
+                        fuel1.max(fuel2)
+                    ));
+                    if fuel1 < fuel2 {
+                        match_result = MatchResult::Bot1Won;
+                        0
+                    } else {
+                        match_result = MatchResult::Bot2Won;
+                        1
+                    }
+                }
+                _ => {
+                    note = Some(format!(
+                        "Tied after {} rounds. Winner chosen by coin toss.",
+                        bot1_moves.len()
+                    ));
+                    // All rounds resulted in a draw
+                    // Choose random winner - number 0 or 1
+                    coin_toss()
+                }
+            }
         }
     };
 
-    // Check for invalid moves
-    let is_bot1_invalid = bot1_moves
-        .iter()
-        .any(|m: &SPROption| *m == SPROption::Invalid);
+    // Check for invalid moves. Bots are only disabled for them in games they say they can
+    // play, so a bot that ends up in another game isn't disabled for every game.
+    let is_bot1_invalid = bot1_moves.iter().any(|m| *m == invalid);
 
-    if is_bot1_invalid {
+    if is_bot1_invalid && bot1.games.contains(&game.kind()) {
         disable_bot(bot1.id, db_pool).await?;
     }
-    let is_bot2_invalid = bot2_moves.iter().any(|m| *m == SPROption::Invalid);
-    if is_bot2_invalid {
+    let is_bot2_invalid = bot2_moves.iter().any(|m| *m == invalid);
+    if is_bot2_invalid && bot2.games.contains(&game.kind()) {
         disable_bot(bot2.id, db_pool).await?;
     }
 
+    // A double forfeit isn't a result, so it doesn't count towards either bot's rating.
+    if !double_forfeit {
+        leaderboard::record_match_result(
+            db_pool,
+            bot1.id,
+            bot2.id,
+            match_result,
+            [&bot1_fuel, &bot2_fuel],
+        )
+        .await?;
+    }
+
     let participant_outcomes = vec![
         ParticipantOutcome {
             name: bot1.name.clone(),
             moves: bot1_moves.clone(),
-            winner: winner_bot == 0,
+            fuel: bot1_fuel.clone(),
+            annotations: bot1_annotations.clone(),
+            winner: !draw && winner_bot == 0,
         },
         ParticipantOutcome {
             name: bot2.name.clone(),
             moves: bot2_moves.clone(),
-            winner: winner_bot == 1,
+            fuel: bot2_fuel.clone(),
+            annotations: bot2_annotations.clone(),
+            winner: !draw && winner_bot == 1,
         },
     ];
 
@@ -824,18 +2108,73 @@ async fn run_match(
         match_id: match_id.clone(),
         state: MatchState::Finished,
         winner: winner_bot,
+        draw,
         note,
         participants: participant_outcomes,
     });
 }
 
-pub async fn create_tournament(
+/// Sets up and saves a tournament, or returns `None` if fewer than two bots can play its game.
+pub async fn create_tournament<G: Game>(
+    game: G,
     db_pool: &ConnectionPool,
     bucket_name: &String,
-) -> Result<Tournament> {
-    let mut bots = get_bots(db_pool, bucket_name).await?;
-    bots.shuffle(&mut rand::thread_rng());
+    config: &TournamentConfig,
+) -> Result<Option<Tournament<G>>> {
+    let mut config = config.clone();
+    // 53 bits, so the seed survives being read as a JavaScript number.
+    let tournament_seed = *config
+        .seed
+        .get_or_insert_with(|| rand::random::<u64>() >> 11);
+    println!("Tournament seed: {}", tournament_seed);
+    // Fill in the game's rules where they're the same for every match, so the client can
+    // show them. Matches are played the same either way.
+    let rules = &mut config.match_rules;
+    let (min_rounds, max_rounds) = game.round_range();
+    if rules.rounds.is_none() && min_rounds == max_rounds {
+        rules.rounds = Some(min_rounds);
+    }
+    if let (None, Some(rounds)) = (rules.win_threshold, rules.rounds) {
+        rules.win_threshold = game.win_threshold(rounds);
+    }
+    let mut bots = get_bots(db_pool, bucket_name, &config).await?;
+    if bots.len() < 2 {
+        println!(
+            "Only {} bots can play {:?}, not starting a tournament.",
+            bots.len(),
+            config.game
+        );
+        return Ok(None);
+    }
+    bots.shuffle(&mut seed::seeded_rng(seed::derive_seed(
+        tournament_seed,
+        "shuffle",
+    )));
+
+    let starting_matches = match config.format {
+        TournamentFormat::SingleElimination => create_single_elimination_matches(&bots),
+        TournamentFormat::DoubleElimination => create_double_elimination_matches(&bots),
+        TournamentFormat::RoundRobin => create_round_robin_matches(&bots),
+        TournamentFormat::Swiss => create_swiss_round::<G::Move>(&bots, &vec![], &vec![], 1),
+    };
+    let mut tournament = Tournament {
+        game,
+        id: None,
+        config,
+        starting_matches,
+        match_updates: vec![],
+        standings: vec![],
+        match_db_ids: HashMap::new(),
+    };
+    if tournament.keeps_standings() {
+        tournament.standings =
+            standings::compute_standings(&tournament.game, &tournament.bot_names(), &[]);
+    }
+    history::save_tournament(db_pool, &mut tournament).await?;
+    return Ok(Some(tournament));
+}
 
+fn create_single_elimination_matches(bots: &Vec<BotDetails>) -> Vec<Match> {
     let num_bots = bots.len() as u32;
     let pow_two = 2_u32.pow(((num_bots as f32).log2()).ceil() as u32);
     let byes = pow_two - num_bots;
@@ -854,6 +2193,8 @@ pub async fn create_tournament(
             id: match_id,
             tournament_round_text: "1".to_string(),
             next_match_id: None,
+            next_loser_match_id: None,
+            bracket: Bracket::Upper,
             participants: vec![bot1.clone(), bot2.clone()],
             state: MatchState::NotStarted,
         };
@@ -868,6 +2209,8 @@ pub async fn create_tournament(
             id: match_id,
             tournament_round_text: "1".to_string(),
             next_match_id: None,
+            next_loser_match_id: None,
+            bracket: Bracket::Upper,
             participants: vec![bot.clone()],
             state: MatchState::Bye,
         };
@@ -886,6 +2229,8 @@ pub async fn create_tournament(
                 id: match_id.clone(),
                 tournament_round_text: round.to_string(),
                 next_match_id: None,
+                next_loser_match_id: None,
+                bracket: Bracket::Upper,
                 participants: vec![],
                 state: MatchState::NotStarted,
             };
@@ -898,10 +2243,265 @@ pub async fn create_tournament(
         round += 1;
     }
     all_matches.append(&mut last_round_matches);
-    return Ok(Tournament {
-        starting_matches: all_matches,
-        match_updates: vec![],
-    });
+    return all_matches;
+}
+
+/// Builds a double elimination bracket: the single elimination bracket becomes the
+/// winners' (upper) bracket, and losers drop into a losers' (lower) bracket. The two
+/// bracket champions meet in a grand final, followed by a reset match if the winners'
+/// bracket champion loses the grand final.
+///
+/// Matches are ordered so that every match comes after the matches feeding into it.
+fn create_double_elimination_matches(bots: &Vec<BotDetails>) -> Vec<Match> {
+    let upper_matches = create_single_elimination_matches(bots);
+    if bots.len() < 2 {
+        return upper_matches;
+    }
+
+    let mut upper_rounds: Vec<Vec<Match>> = vec![];
+    for upper_match in upper_matches {
+        let round: usize = upper_match.tournament_round_text.parse().unwrap_or(1);
+        if upper_rounds.len() < round {
+            upper_rounds.push(vec![]);
+        }
+        upper_rounds[round - 1].push(upper_match);
+    }
+    let num_upper_rounds = upper_rounds.len();
+
+    let new_lower_round = |round: usize, count: usize| -> Vec<Match> {
+        (0..count)
+            .map(|i| {
+                Match::new_empty(
+                    format!("lower-{}-{}", round, i + 1),
+                    format!("L{}", round),
+                    Bracket::Lower,
+                )
+            })
+            .collect()
+    };
+
+    let mut lower_rounds: Vec<Vec<Match>> = vec![];
+    if num_upper_rounds >= 2 {
+        // Losers of the first round play each other.
+        let first_round = new_lower_round(1, upper_rounds[0].len() / 2);
+        for (i, upper_match) in upper_rounds[0].iter_mut().enumerate() {
+            upper_match.set_next_loser_match_id(first_round[i / 2].id.clone());
+        }
+        lower_rounds.push(first_round);
+
+        for upper_round in 1..num_upper_rounds {
+            // Lower bracket winners meet the losers dropping down from the upper round.
+            // Losers drop in reverse order to avoid immediate rematches.
+            let round_number = lower_rounds.len() + 1;
+            let previous = lower_rounds.last_mut().unwrap();
+            let drop_round = new_lower_round(round_number, previous.len());
+            for (i, lower_match) in previous.iter_mut().enumerate() {
+                lower_match.set_next_match_id(drop_round[i].id.clone());
+            }
+            let num_drop_matches = drop_round.len();
+            for (i, upper_match) in upper_rounds[upper_round].iter_mut().enumerate() {
+                upper_match
+                    .set_next_loser_match_id(drop_round[num_drop_matches - 1 - i].id.clone());
+            }
+            lower_rounds.push(drop_round);
+
+            if upper_round < num_upper_rounds - 1 {
+                // Lower bracket winners play each other.
+                let round_number = lower_rounds.len() + 1;
+                let previous = lower_rounds.last_mut().unwrap();
+                let next_round = new_lower_round(round_number, previous.len() / 2);
+                for (i, lower_match) in previous.iter_mut().enumerate() {
+                    lower_match.set_next_match_id(next_round[i / 2].id.clone());
+                }
+                lower_rounds.push(next_round);
+            }
+        }
+    }
+
+    let mut grand_final = Match::new_empty(
+        "grand-final".to_string(),
+        "Grand final".to_string(),
+        Bracket::GrandFinal,
+    );
+    let reset = Match::new_empty(
+        "grand-final-reset".to_string(),
+        "Grand final reset".to_string(),
+        Bracket::GrandFinalReset,
+    );
+    grand_final.set_next_match_id(reset.id.clone());
+    grand_final.set_next_loser_match_id(reset.id.clone());
+
+    let upper_final = &mut upper_rounds[num_upper_rounds - 1][0];
+    upper_final.set_next_match_id(grand_final.id.clone());
+    match lower_rounds.last_mut() {
+        Some(lower_final) => lower_final[0].set_next_match_id(grand_final.id.clone()),
+        // With only two bots there's no losers' bracket, the loser goes straight to the grand final.
+        None => upper_final.set_next_loser_match_id(grand_final.id.clone()),
+    }
+
+    // Interleave the brackets so each lower round comes after the upper round it takes losers from.
+    let mut matches: Vec<Match> = vec![];
+    let mut lower_rounds = lower_rounds.into_iter();
+    for (i, upper_round) in upper_rounds.into_iter().enumerate() {
+        matches.extend(upper_round);
+        if i >= 1 {
+            matches.extend(lower_rounds.next().unwrap_or_default());
+            matches.extend(lower_rounds.next().unwrap_or_default());
+        }
+    }
+    matches.push(grand_final);
+    matches.push(reset);
+    matches
+}
+
+/// Schedules every bot against every other bot using the circle method, so that each
+/// round has every bot playing at most once.
+fn create_round_robin_matches(bots: &Vec<BotDetails>) -> Vec<Match> {
+    let mut slots: Vec<Option<&BotDetails>> = bots.iter().map(Some).collect();
+    if slots.len() % 2 == 1 {
+        // Pairing with the empty slot means sitting out the round.
+        slots.push(None);
+    }
+
+    let mut matches: Vec<Match> = vec![];
+    let num_slots = slots.len();
+    for round in 1..num_slots {
+        for i in 0..(num_slots / 2) {
+            if let (Some(bot1), Some(bot2)) = (slots[i], slots[num_slots - 1 - i]) {
+                matches.push(Match {
+                    id: format!("{}-{}", bot1.name, bot2.name),
+                    tournament_round_text: round.to_string(),
+                    next_match_id: None,
+                    next_loser_match_id: None,
+                    bracket: Bracket::Upper,
+                    participants: vec![bot1.clone(), bot2.clone()],
+                    state: MatchState::NotStarted,
+                });
+            }
+        }
+        // Keep the first slot fixed and rotate the rest.
+        slots[1..].rotate_right(1);
+    }
+    matches
+}
+
+/// Most pairings tried when looking for a round without rematches, before settling for one
+/// with rematches.
+const MAX_PAIRING_STEPS: u32 = 100_000;
+
+/// Pairs bots in order of rank, each with the highest-ranked bot it hasn't played yet that
+/// still leaves a way to pair everyone else without rematches. Returns None if there's no
+/// such pairing, or it couldn't be found in `MAX_PAIRING_STEPS`.
+fn pair_without_rematches<'a>(
+    unpaired: &[&'a BotDetails],
+    has_played: &dyn Fn(&BotDetails, &BotDetails) -> bool,
+    steps: &mut u32,
+) -> Option<Vec<(&'a BotDetails, &'a BotDetails)>> {
+    let (bot1, rest) = match unpaired.split_first() {
+        Some(split) => split,
+        None => return Some(vec![]),
+    };
+    for (i, bot2) in rest.iter().enumerate() {
+        if *steps >= MAX_PAIRING_STEPS {
+            return None;
+        }
+        *steps += 1;
+        if has_played(bot1, bot2) {
+            continue;
+        }
+        let mut remaining = rest.to_vec();
+        remaining.remove(i);
+        if let Some(mut pairs) = pair_without_rematches(&remaining, has_played, steps) {
+            pairs.insert(0, (*bot1, *bot2));
+            return Some(pairs);
+        }
+    }
+    return None;
+}
+
+/// Pairs bots for one round of a Swiss tournament. Bots are ranked by the current
+/// standings and each is paired with the highest-ranked bot it hasn't played yet, going
+/// back on earlier pairs if that's what it takes to avoid a rematch. Rematches only happen
+/// when there's no way around them, and then each bot is paired with the highest-ranked
+/// remaining bot it hasn't played, or the highest-ranked one if it's played them all. With
+/// an odd number of bots, the lowest-ranked bot that hasn't had a bye yet sits out the
+/// round.
+fn create_swiss_round<M>(
+    bots: &Vec<BotDetails>,
+    standings: &Vec<StandingsEntry>,
+    outcomes: &Vec<MatchOutcome<M>>,
+    round: u32,
+) -> Vec<Match> {
+    // Bots without a standings entry (i.e. in the first round) keep their shuffled order.
+    let rank_of = |bot: &BotDetails| {
+        standings
+            .iter()
+            .position(|entry| entry.name == bot.name)
+            .unwrap_or(usize::MAX)
+    };
+    let mut unpaired: Vec<&BotDetails> = bots.iter().collect();
+    unpaired.sort_by_key(|bot| rank_of(bot));
+
+    let has_played = |bot1: &BotDetails, bot2: &BotDetails| {
+        outcomes.iter().any(|outcome| {
+            outcome.participants.len() == 2
+                && outcome.participants.iter().any(|p| p.name == bot1.name)
+                && outcome.participants.iter().any(|p| p.name == bot2.name)
+        })
+    };
+    let has_had_bye = |bot: &BotDetails| {
+        outcomes.iter().any(|outcome| {
+            outcome.state == MatchState::Bye && outcome.participants[0].name == bot.name
+        })
+    };
+
+    let mut matches: Vec<Match> = vec![];
+    if unpaired.len() % 2 == 1 {
+        let bye_index = unpaired
+            .iter()
+            .rposition(|bot| !has_had_bye(bot))
+            .unwrap_or(unpaired.len() - 1);
+        let bot = unpaired.remove(bye_index);
+        matches.push(Match {
+            id: format!("{}-{}-bye", round, bot.name),
+            tournament_round_text: round.to_string(),
+            next_match_id: None,
+            next_loser_match_id: None,
+            bracket: Bracket::Upper,
+            participants: vec![bot.clone()],
+            state: MatchState::Bye,
+        });
+    }
+
+    let mut steps = 0;
+    let pairs = match pair_without_rematches(&unpaired, &has_played, &mut steps) {
+        Some(pairs) => pairs,
+        None => {
+            let mut pairs = vec![];
+            while !unpaired.is_empty() {
+                let bot1 = unpaired.remove(0);
+                let opponent_index = unpaired
+                    .iter()
+                    .position(|bot2| !has_played(bot1, bot2))
+                    .unwrap_or(0);
+                pairs.push((bot1, unpaired.remove(opponent_index)));
+            }
+            pairs
+        }
+    };
+
+    for (bot1, bot2) in pairs {
+        matches.push(Match {
+            id: format!("{}-{}-{}", round, bot1.name, bot2.name),
+            tournament_round_text: round.to_string(),
+            next_match_id: None,
+            next_loser_match_id: None,
+            bracket: Bracket::Upper,
+            participants: vec![bot1.clone(), bot2.clone()],
+            state: MatchState::NotStarted,
+        });
+    }
+    matches
 }
 
 async fn disable_bot(bot_id: Option<i32>, db_pool: &ConnectionPool) -> Result<u64> {
@@ -918,7 +2518,7 @@ async fn disable_bot(bot_id: Option<i32>, db_pool: &ConnectionPool) -> Result<u6
     return Ok(count);
 }
 
-async fn save_bot_code(bucket_name: &String, bytes: Vec<u8>) -> Result<String> {
+async fn save_bot_code(bucket_name: &String, bytes: Vec<u8>, is_archive: bool) -> Result<String> {
     let shared_config = aws_config::load_defaults(BehaviorVersion::v2024_03_28()).await;
     let minio_root = env::var("MINIO_ROOT_USER");
     let minio_secret = env::var("MINIO_ROOT_PASSWORD");
@@ -948,7 +2548,12 @@ async fn save_bot_code(bucket_name: &String, bytes: Vec<u8>) -> Result<String> {
     }
 
     let hash = sha256::digest(&bytes);
-    let key = format!("{}.wasm", hash);
+    let (extension, content_type) = if is_archive {
+        ("zip", "application/zip")
+    } else {
+        ("wasm", "application/wasm")
+    };
+    let key = format!("{}.{}", hash, extension);
     let body = ByteStream::from(bytes);
 
     println!("{}", &key);
@@ -956,6 +2561,7 @@ async fn save_bot_code(bucket_name: &String, bytes: Vec<u8>) -> Result<String> {
         .put_object()
         .bucket(bucket_name)
         .key(&key)
+        .content_type(content_type)
         .body(body)
         .send()
         .await